@@ -1,32 +1,165 @@
 //! Handles collision
 
 use crate::prelude::*;
+use std::collections::HashMap;
+
+/// A marker component for entities indexed in the [`ColliderGrid`] by their [`Vec2i32`]
+pub struct Collider;
+
+impl Component for Collider {
+    const STORAGE: StorageKind = StorageKind::Marker;
+}
 
 /// A global resource for indexing grid-wise location for all entities
 /// in the arena.
+///
+/// Kept in sync by the hooks from [`add_collider_hooks`] when positions are
+/// inserted, and by [`sync_collider_grid_system`] when they are mutated.
+/// Systems queuing a move or a spawn with the commands [`ColliderGrid::reserve`]
+/// the cell first, so two entities are never queued into the same one.
 #[derive(Default)]
-pub struct ColliderGrid(pub Vec<Vec<Option<Entity>>>);
+pub struct ColliderGrid {
+    cells: Vec<Vec<Option<Entity>>>,
+    /// The cell each indexed entity is in
+    indexed: HashMap<Entity, Vec2usize>,
+    /// The cells promised to entities that are not inserted yet
+    reserved: HashSet<Vec2usize>,
+    /// The [`World::change_tick`] of the last sync
+    synced: Tick,
+}
 
 impl ColliderGrid {
-    /// Index an entity
+    /// An empty grid of `size` cells
+    pub fn new(size: Vec2usize) -> ColliderGrid {
+        ColliderGrid { cells: vec![vec![None; size.1]; size.0], ..Default::default() }
+    }
+
+    /// Index an entity at `pos`, moving it from where it was indexed before
     ///
-    /// [`Some(Entity)`] if an entity exists at a location `pos`
-    /// [`None`] if there is nothing at the location `pos`
-    pub fn insert(&mut self, pos: Vec2usize, new: Option<Entity>) {
-        let Some(slot) = self.0.get_mut(pos.0).and_then(|a| a.get_mut(pos.1)) else { return };
-        *slot = new
+    /// Replaces any other entity at that location.
+    pub fn insert(&mut self, pos: Vec2usize, entity: Entity) {
+        self.remove(entity);
+        let Some(slot) = self.cells.get_mut(pos.0).and_then(|a| a.get_mut(pos.1)) else { return };
+        *slot = Some(entity);
+        self.indexed.insert(entity, pos);
+    }
+
+    /// Remove an entity from the grid, unless another entity took its place
+    pub fn remove(&mut self, entity: Entity) {
+        let Some(pos) = self.indexed.remove(&entity) else { return };
+        let Some(slot) = self.cells.get_mut(pos.0).and_then(|a| a.get_mut(pos.1)) else { return };
+        if *slot == Some(entity) {
+            *slot = None;
+        }
     }
 
-    /// Remove an item at `pos` from the grid
-    pub fn remove(&mut self, pos: Vec2usize) {
-        let Some(slot) = self.0.get_mut(pos.0).and_then(|a| a.get_mut(pos.1)) else { return };
-        *slot = None;
+    /// Reserve the cell at `pos` for an entity that is moved or spawned there by the commands
+    ///
+    /// Returns `false` if the cell is taken or already reserved. The reservations
+    /// are released by the next [`sync_collider_grid_system`], once the commands are applied.
+    pub fn reserve(&mut self, pos: Vec2usize) -> bool {
+        self.get(pos).is_none() && self.reserved.insert(pos)
     }
 
     /// Get entity at `pos`
     ///
     /// Returns [`None`] if there is nothing there.
     pub fn get(&self, pos: Vec2usize) -> Option<Entity> {
-        self.0.get(pos.0).and_then(|a| a.get(pos.1)).copied().flatten()
+        self.cells.get(pos.0).and_then(|a| a.get(pos.1)).copied().flatten()
+    }
+}
+
+/// Index the entity in the [`ColliderGrid`] if it has both a [`Collider`] and a position
+fn index_collider(world: &mut World, entity: Entity) {
+    let Ok(&pos) = world.components::<Vec2i32>().get(world.entities(), entity) else { return };
+    if !world.has::<Collider>(entity) {
+        return;
+    }
+    let cell = arena_collider_pos(&world.resource::<Arena>().0, pos);
+    world.resource_mut::<ColliderGrid>().insert(cell, entity);
+}
+
+/// Remove the entity from the [`ColliderGrid`]
+fn unindex_collider(world: &mut World, entity: Entity) {
+    world.resource_mut::<ColliderGrid>().remove(entity);
+}
+
+/// Register the hooks keeping [`ColliderGrid`] in sync with the [`Collider`]s
+///
+/// [`Arena`] and [`ColliderGrid`] must be inserted before any collider.
+pub fn add_collider_hooks(world: &mut World) {
+    let hooks = world.hooks_mut::<Collider>();
+    hooks.on_insert = Some(index_collider);
+    hooks.on_remove = Some(unindex_collider);
+    let hooks = world.hooks_mut::<Vec2i32>();
+    hooks.on_insert = Some(index_collider);
+    hooks.on_remove = Some(unindex_collider);
+}
+
+/// Move the colliders whose [`Vec2i32`] was mutated since the last sync in the [`ColliderGrid`]
+///
+/// Inserted positions are already indexed by the hooks, this catches the ones
+/// written through [`Query::get_mut`] and the like.
+pub fn sync_collider_grid_system(world: &World) {
+    let arena = world.resource::<Arena>();
+    let mut collider_grid = world.resource_mut::<ColliderGrid>();
+    let positions = world.components::<Vec2i32>();
+    for (entity, &pos) in positions.changed_since(world.entities(), collider_grid.synced) {
+        if world.has::<Collider>(entity) {
+            collider_grid.insert(arena_collider_pos(&arena.0, pos), entity);
+        }
+    }
+    // Positions changed later this tick are picked up by the next sync.
+    collider_grid.synced = world.change_tick();
+    collider_grid.reserved.clear();
+}
+
+/// Register the system syncing the [`ColliderGrid`] to the [`Schedule`]
+pub fn add_collision_systems(schedule: &mut Schedule) {
+    schedule.add_system(Stage::PostUpdate, "sync_collider_grid", sync_collider_grid_system).after("propagate_position").before("enemy_follow").reads::<Vec2i32>().reads_resource::<Arena>().writes_resource::<ColliderGrid>();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn grid_follows_mutated_positions() {
+        let mut world = World::default();
+        world.insert_resource(Arena((2, 2)));
+        world.insert_resource(ColliderGrid::new((5, 5)));
+        add_collider_hooks(&mut world);
+        let entity = world.spawn();
+        world.insert(entity, Collider).unwrap();
+        world.insert(entity, (0, 0)).unwrap();
+        sync_collider_grid_system(&world);
+        world.maintain();
+
+        *world.components_mut::<Vec2i32>().get_mut(world.entities(), entity).unwrap() = (1, -1);
+        sync_collider_grid_system(&world);
+
+        let collider_grid = world.resource::<ColliderGrid>();
+        assert_eq!(collider_grid.get((2, 2)), None);
+        assert_eq!(collider_grid.get((3, 1)), Some(entity));
+    }
+
+    #[test]
+    fn reserved_cells_are_not_given_twice() {
+        let mut world = World::default();
+        world.insert_resource(Arena((2, 2)));
+        world.insert_resource(ColliderGrid::new((5, 5)));
+        add_collider_hooks(&mut world);
+        let entity = world.spawn();
+        world.insert(entity, Collider).unwrap();
+        world.insert(entity, (0, 0)).unwrap();
+
+        let mut collider_grid = world.resource_mut::<ColliderGrid>();
+        assert!(!collider_grid.reserve((2, 2)));
+        assert!(collider_grid.reserve((3, 2)));
+        assert!(!collider_grid.reserve((3, 2)));
+        drop(collider_grid);
+
+        sync_collider_grid_system(&world);
+        assert!(world.resource_mut::<ColliderGrid>().reserve((3, 2)));
     }
 }
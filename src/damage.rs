@@ -2,6 +2,75 @@
 
 use crate::prelude::*;
 
+/// A component for the health of an entity, it is killed once it reaches 0
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Health(pub i32);
+
+impl Component for Health {}
+
+impl Reflect for Health {
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![("value", self.0.to_string())]
+    }
+
+    fn set_field(&mut self, name: &str, value: &str) -> Result<(), ReflectError> {
+        match name {
+            "value" => self.0 = parse_field(name, value)?,
+            _ => return Err(ReflectError::UnknownField(name.to_owned())),
+        }
+        Ok(())
+    }
+}
+
+/// A component for the maximum [`Health`] of an entity
+#[derive(Clone)]
+pub struct MaxHealth(pub i32);
+
+impl Component for MaxHealth {}
+
+impl Reflect for MaxHealth {
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![("value", self.0.to_string())]
+    }
+
+    fn set_field(&mut self, name: &str, value: &str) -> Result<(), ReflectError> {
+        match name {
+            "value" => self.0 = parse_field(name, value)?,
+            _ => return Err(ReflectError::UnknownField(name.to_owned())),
+        }
+        Ok(())
+    }
+}
+
+/// A component for the timer that is reset every time the entity is damaged
+///
+/// Only used for display, so it runs in real time regardless of [`Time::scale`].
+#[derive(Clone)]
+pub struct DamagedTimer(pub Timer);
+
+impl Component for DamagedTimer {}
+
+impl AsMut<Timer> for DamagedTimer {
+    fn as_mut(&mut self) -> &mut Timer {
+        &mut self.0
+    }
+}
+
+impl Reflect for DamagedTimer {
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        self.0.fields()
+    }
+
+    fn set_field(&mut self, name: &str, value: &str) -> Result<(), ReflectError> {
+        self.0.set_field(name, value)
+    }
+}
+
+/// A component for the color displayed while [`DamagedTimer`] has not finished
+pub struct DamagedColor(pub Color);
+
+impl Component for DamagedColor {}
+
 /// Damage event
 ///
 /// This event is manually emitted by any system that needs to declare some
@@ -15,8 +84,8 @@ pub struct Damage {
 
 /// Kill event
 ///
-/// This event is emitted when an entity with the [`Health`] component reaches
-/// or less than 0.
+/// This event is emitted once when an entity with the [`Health`] component
+/// reaches or less than 0.
 pub struct Kill {
     /// The [`Entity`] that have been killed
     pub target: Entity,
@@ -25,23 +94,33 @@ pub struct Kill {
 /// This system handle emits bell notification when there are any [`Damage`]
 /// event in the buffer.
 /// If an entity's health reaches or less than zero, emit the [`Kill`] event.
-/// If an entity has `damaged_timer`, it will be reset. This can be used to
+/// If an entity has [`DamagedTimer`], it will be reset. This can be used to
 /// implemented animations.
-pub fn damage_system(stdout: &mut Stdout, damage_events: &Events<Damage>, kill_events: &mut Events<Kill>, entities: &Entities, hps: &mut Components<Health>, damaged_timer: &mut Components<Timer>) {
+pub fn damage_system(mut damage_events: EventReader<Damage>, mut kill_events: EventWriter<Kill>, mut query: Query<(&mut Health, Option<&mut DamagedTimer>)>) {
+    #[cfg(feature = "terminal")]
     if !damage_events.is_empty() {
         // Bell notification
-        let _ = stdout.write_all(b"\x07");
+        let _ = io::stdout().write_all(b"\x07");
     }
-    for damage in damage_events {
-        let Ok(hp) = hps.get_mut(entities, damage.target) else { return };
+    for damage in damage_events.read() {
+        // The target may have been despawned or not spawned yet by its commands.
+        let Ok((Health(hp), damaged_timer)) = query.get_mut(damage.target) else { continue };
 
+        // Damage to an entity that is already killed but not despawned yet does not kill it again.
+        let alive = *hp > 0;
         *hp -= damage.amount;
 
-        if let Ok(timer) = damaged_timer.get_mut(entities, damage.target) {
-            timer.reset();
+        if let Some(timer) = damaged_timer {
+            timer.0.reset();
         }
-        if *hp <= 0 {
-            kill_events.push(Kill { target: damage.target });
+        if alive && *hp <= 0 {
+            kill_events.write(Kill { target: damage.target });
         }
     }
 }
+
+/// Register the damage systems to the [`Schedule`]
+pub fn add_damage_systems(schedule: &mut Schedule) {
+    schedule.add_system(Stage::Update, "damaged_timer", real_timer_system::<DamagedTimer>).run_if(playing);
+    schedule.add_system(Stage::PostUpdate, "damage", damage_system).run_if(playing);
+}
@@ -15,47 +15,88 @@ pub struct DrawInfo {
     pub shape: Shape,
 }
 
+// Drawings are short lived, keep them out of the way of the long lived components.
+impl Component for DrawInfo {
+    const STORAGE: StorageKind = StorageKind::SparseSet;
+}
+
+/// A component for the time left before the drawing is despawned.
+///
+/// Runs in real time regardless of [`Time::scale`].
+pub struct DrawTimer(pub Timer);
+
+impl Component for DrawTimer {
+    const STORAGE: StorageKind = StorageKind::SparseSet;
+}
+
+impl AsMut<Timer> for DrawTimer {
+    fn as_mut(&mut self) -> &mut Timer {
+        &mut self.0
+    }
+}
+
+/// Components of a short lived drawing.
+pub struct EffectBundle {
+    pub draw_info: DrawInfo,
+    pub position: Vec2i32,
+    pub timer: DrawTimer,
+}
+
+impl Bundle for EffectBundle {
+    fn build(self, builder: &mut EntityBuilder) {
+        builder.with(self.draw_info).with(self.position).with(self.timer);
+    }
+}
+
 /// An event emitted by any system to draw something.
 #[derive(Clone)]
 pub struct Draw {
     /// The drawing information.
     pub draw_info: DrawInfo,
-    /// The position.
+    /// The position, relative to `parent` if there is one.
     pub position: Vec2i32,
+    /// The entity the drawing follows.
+    pub parent: Option<Entity>,
     /// Time to persist for.
     pub timer: Timer,
 }
 
 /// Check for [`Draw`] event and spawn the entity for it.
-pub fn spawn_draw_system(spawn_events: &Events<Draw>, entities: &mut Entities, positions: &mut Components<Vec2i32>, draw_infos: &mut Components<DrawInfo>, draw_timer: &mut Components<Timer>) {
-    for spawn in spawn_events {
-        let id = entities.spawn();
-        draw_infos.insert(entities, id, spawn.draw_info.clone()).unwrap();
-        positions.insert(entities, id, spawn.position).unwrap();
-        draw_timer.insert(entities, id, spawn.timer.clone()).unwrap();
+pub fn spawn_draw_system(mut commands: Commands, mut draw_events: EventReader<Draw>, positions: Query<&Vec2i32>) {
+    for spawn in draw_events.read() {
+        let position = match spawn.parent {
+            Some(parent) => {
+                let Ok(parent_pos) = positions.get(parent) else { continue };
+                (parent_pos.0 + spawn.position.0, parent_pos.1 + spawn.position.1)
+            }
+            None => spawn.position,
+        };
+        let mut drawing = commands.spawn_bundle(EffectBundle { draw_info: spawn.draw_info.clone(), position, timer: DrawTimer(spawn.timer.clone()) });
+        if let Some(parent) = spawn.parent {
+            drawing.insert(RelativePosition(spawn.position)).set_parent(parent);
+        }
+    }
+}
+
+/// Despawn the drawings whose [`DrawTimer`] has finished.
+pub fn despawn_draw_system(mut commands: Commands, drawings: Query<(Entity, &DrawTimer)>) {
+    for (id, timer) in &drawings {
+        if timer.0.finished() {
+            commands.despawn_recursive(id);
+        }
     }
 }
 
 /// Draw [`DrawInfo`] to the terminal.
-pub fn draw_system(stdout: &mut StdoutLock, camera: Entity, entities: &mut Entities, positions: &Components<Vec2i32>, draw_infos: &mut Components<DrawInfo>, draw_timer: &Components<Timer>) {
-    let camera_pos = *positions.get(entities, camera).unwrap();
+#[cfg(feature = "terminal")]
+pub fn draw_system(cameras: Query<&Vec2i32, With<Camera>>, drawings: Query<(&Vec2i32, &DrawInfo)>) {
+    let stdout = &mut io::stdout().lock();
+    let camera_pos = *cameras.single().expect("Camera position");
     let terminal_size = terminal::size().expect("Terminal size");
     let terminal_size = (terminal_size.0 as i32, terminal_size.1 as i32);
     let terminal_middle = (terminal_size.0 / 2, terminal_size.1 / 2);
 
-    // Buffer for entities that needed to be respawn. (Solves mut aliasing issue)
-    let mut to_despawn = vec![];
-    for id in entities.iter() {
-        let Ok(pos) = positions.get(entities, id) else { continue };
-        let Ok(draw_info) = draw_infos.get_mut(entities, id) else { continue };
-        let Ok(timer) = draw_timer.get(entities, id) else { continue };
-
-        // Despawn instead if the timer is finishes.
-        if timer.finished() {
-            to_despawn.push(id);
-            continue;
-        }
-
+    for (pos, draw_info) in &drawings {
         match draw_info.shape {
             Shape::Rectangle { w, h } => {
                 // Print line by line instead of character to reduce bottleneck.
@@ -69,14 +110,17 @@ pub fn draw_system(stdout: &mut StdoutLock, camera: Entity, entities: &mut Entit
                         continue;
                     }
                     let mut content = (&line[..]).stylize();
-                    *content.style_mut() = draw_info.sprite.style;
+                    content.style_mut().foreground_color = draw_info.sprite.color.map(Into::into);
                     let _ = queue!(stdout, cursor::MoveTo(terminal_pos.0 as u16, y as u16 + terminal_pos.1 as u16), style::PrintStyledContent(content));
                 }
             }
         }
     }
+}
 
-    for id in to_despawn {
-        let _ = entities.despawn(id);
-    }
+/// Register the drawing systems to the [`Schedule`]
+pub fn add_draw_systems(schedule: &mut Schedule) {
+    schedule.add_system(Stage::Update, "draw_timer", real_timer_system::<DrawTimer>);
+    schedule.add_system(Stage::PostUpdate, "spawn_draw", spawn_draw_system);
+    schedule.add_system(Stage::PostUpdate, "despawn_draw", despawn_draw_system);
 }
@@ -1,5 +1,55 @@
 //! The core ECS implementation.
 
+use std::{
+    any::{Any, TypeId},
+    collections::{BTreeMap, HashMap},
+    sync::{
+        Mutex, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError,
+        atomic::{AtomicIsize, Ordering},
+    },
+};
+
+mod bundle;
+mod commands;
+mod event;
+mod hierarchy;
+mod hooks;
+mod query;
+mod reflect;
+mod schedule;
+mod system;
+
+pub use bundle::*;
+pub use commands::*;
+pub use event::*;
+pub use hierarchy::*;
+pub use hooks::*;
+pub use query::*;
+pub use reflect::*;
+pub use schedule::*;
+pub use system::*;
+
+/// A type that can be stored in [`Components`] and used in a [`World`].
+///
+/// Unit structs work as markers, found with the [`With`] and [`Without`]
+/// filters, and should use [`StorageKind::Marker`].
+pub trait Component: Send + Sync + 'static {
+    /// How the [`Components`] of this type are laid out.
+    const STORAGE: StorageKind = StorageKind::Indexed;
+}
+
+/// A set of components, where each component type is given a single bit.
+pub type BitField = u128;
+
+/// The achetype of an entity.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EntityAchetype {
+    /// Whether the entity at this index is valid.
+    valid: bool,
+    /// The components this entity has.
+    components: BitField,
+}
+
 /// The entity.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Entity {
@@ -9,11 +59,69 @@ pub struct Entity {
     generation: u32,
 }
 
+impl std::fmt::Display for Entity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
+
+/// Errors returned by [`Entities`] and [`Components`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EcsError {
+    /// The entity's index has never been spawned.
+    EntityNotFound(Entity),
+    /// The entity has been despawned and its slot now has a newer generation.
+    StaleGeneration {
+        /// The entity that was used.
+        entity: Entity,
+        /// The current generation at the entity's index.
+        current: u32,
+    },
+    /// The entity is valid but does not have the component.
+    MissingComponent {
+        /// The entity that was used.
+        entity: Entity,
+        /// The name of the component type.
+        type_name: &'static str,
+    },
+    /// The parent is the entity itself or one of its descendants.
+    HierarchyCycle {
+        /// The entity that would be the child.
+        entity: Entity,
+        /// The entity that would be the parent.
+        parent: Entity,
+    },
+}
+
+impl std::error::Error for EcsError {}
+
+impl std::fmt::Display for EcsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EcsError::EntityNotFound(entity) => write!(f, "Entity {entity} does not exists"),
+            EcsError::StaleGeneration { entity, current } => write!(f, "Entity {entity} has been despawned (current generation is {current})"),
+            EcsError::MissingComponent { entity, type_name } => write!(f, "Entity {entity} does not have the component {type_name}"),
+            EcsError::HierarchyCycle { entity, parent } => write!(f, "Entity {entity} can not be a child of itself or its descendant {parent}"),
+        }
+    }
+}
+
 /// The entities index.
 #[derive(Default)]
 pub struct Entities {
     /// List of entity's index that has been despawned and its index can be used later.
     unused: Vec<usize>,
+    /// List of entities that has been despawned but their components has not been
+    /// cleared yet.
+    ///
+    /// Their index will be moved to `unused` by [`Entities::maintain`].
+    despawned: Vec<Entity>,
+    /// The amount of `unused` slots that are not reserved yet.
+    ///
+    /// [`Entities::reserve`] takes slots from the end of `unused` and goes
+    /// negative once it runs out, counting the new slots reserved past the end.
+    /// They become valid on [`Entities::flush`].
+    free_cursor: AtomicIsize,
     /// The generation of each entity.
     ///
     /// If [`Entity::generation`] at its index do not match, that means that the
@@ -21,24 +129,29 @@ pub struct Entities {
     generations: Vec<u32>,
     /// The achetype of each entity.
     ///
-    /// Currently only stores `bool` which tells if the entity at that index is
-    /// valid or not.
-    /// In the future, the achetype can be extended to stores additional informations,
-    /// such as, the components each entity contains for optimization.
-    achetype: Vec<bool>,
+    /// Tells if the entity at that index is valid and which components it has,
+    /// so iterating can skip entities without probing each [`Components`].
+    achetype: Vec<EntityAchetype>,
 }
 
 impl Entities {
     /// Create a new [`Entity`]
     pub fn spawn(&mut self) -> Entity {
+        self.flush();
+        let entity = self.spawn_unreserved();
+        *self.free_cursor.get_mut() = self.unused.len() as isize;
+        entity
+    }
+
+    fn spawn_unreserved(&mut self) -> Entity {
         // Check if there are any slot that we can reuse
         if self.unused.is_empty() {
             // `unused` is empty, create a new slot.
 
             // Initialize the generation to 0
             self.generations.push(0);
-            // Initialize the achetype to `true` because the entity is valid.
-            self.achetype.push(true);
+            // Initialize the achetype as valid without any components.
+            self.achetype.push(EntityAchetype { valid: true, components: 0 });
             Entity { index: self.generations.len() - 1, generation: 0 }
         } else {
             // `unused` contains something, reuse the slot.
@@ -49,9 +162,9 @@ impl Entities {
             // already incremented it on despawn.
             let generation = *self.generations.get(index).expect("generation for this entity should already exists");
 
-            // Set the achetype to `true` since we're reusing the slot.
+            // Set the achetype to valid since we're reusing the slot.
             let achetype = self.achetype.get_mut(index).expect("achetype for this entity should already exists");
-            *achetype = true;
+            achetype.valid = true;
 
             Entity { index, generation }
         }
@@ -59,97 +172,901 @@ impl Entities {
 
     /// Despawn an existing [`Entity`]
     ///
-    /// Returns [`Err(())`] if entity does not exists or its generation do not match.
-    /// In the future, we can use an actual error type for better user experience.
-    pub fn despawn(&mut self, entity: Entity) -> Result<(), ()> {
-        let Some(generation) = self.generations.get_mut(entity.index) else {
-            return Err(());
-        };
-        if *generation != entity.generation {
-            return Err(());
-        }
+    /// Returns [`EcsError::EntityNotFound`] if entity does not exists or
+    /// [`EcsError::StaleGeneration`] if its generation do not match.
+    pub fn despawn(&mut self, entity: Entity) -> Result<(), EcsError> {
+        self.check(entity)?;
+        let generation = self.generations.get_mut(entity.index).expect("generation is checked");
 
-        // The entity is no longer valid; its achetype is setted to `false`.
-        *self.achetype.get_mut(entity.index).expect("achetype for this entity should already exists") = false;
+        // The entity is no longer valid and has no components.
+        let achetype = self.achetype.get_mut(entity.index).expect("achetype for this entity should already exists");
+        achetype.valid = false;
+        achetype.components = 0;
 
         // Increment the generation and many APIs will now error on generation mismatch.
         *generation += 1;
 
+        // The slot can not be reused until its components are cleared.
+        self.despawned.push(entity);
+
+        Ok(())
+    }
+
+    /// Clear all components of despawned entities from the `registry` and
+    /// allow their index to be reused by [`Entities::spawn`].
+    ///
+    /// The removed entities from the previous frame are forgotten first, so
+    /// [`Components::removed`] only contains this frame's removals.
+    ///
+    /// Should be called once per frame.
+    pub fn maintain(&mut self, registry: &mut impl ComponentRegistry) {
+        self.flush();
+        registry.clear_removed();
+        for entity in self.despawned.drain(..) {
+            registry.remove_all(entity);
+            self.unused.push(entity.index);
+        }
+        *self.free_cursor.get_mut() = self.unused.len() as isize;
+    }
+
+    /// Reserve an [`Entity`] without mutable access.
+    ///
+    /// The entity is not valid until [`Entities::flush`], which happens on
+    /// the next [`World::apply_commands`], [`World::spawn`] or [`World::maintain`].
+    pub fn reserve(&self) -> Entity {
+        let cursor = self.free_cursor.fetch_sub(1, Ordering::Relaxed);
+        if cursor > 0 {
+            let index = self.unused[cursor as usize - 1];
+            Entity { index, generation: self.generations[index] }
+        } else {
+            Entity { index: self.generations.len() + (-cursor) as usize, generation: 0 }
+        }
+    }
+
+    /// Make every reserved [`Entity`] valid.
+    pub fn flush(&mut self) {
+        let cursor = *self.free_cursor.get_mut();
+        let reused = cursor.max(0) as usize;
+        for index in self.unused.drain(reused..) {
+            self.achetype[index].valid = true;
+        }
+        for _ in cursor..0 {
+            self.spawn_unreserved();
+        }
+        *self.free_cursor.get_mut() = self.unused.len() as isize;
+    }
+
+    /// Check if an [`Entity`] exists, returning the reason if it does not.
+    pub fn check(&self, entity: Entity) -> Result<(), EcsError> {
+        let Some(current) = self.generations.get(entity.index).copied() else {
+            return Err(EcsError::EntityNotFound(entity));
+        };
+        if current != entity.generation {
+            return Err(EcsError::StaleGeneration { entity, current });
+        }
+        if !self.achetype.get(entity.index).is_some_and(|a| a.valid) {
+            return Err(EcsError::EntityNotFound(entity));
+        }
         Ok(())
     }
 
     /// Check if an [`Entity`] exists.
     pub fn exists(&self, entity: Entity) -> bool {
-        self.generations.get(entity.index).map(|g| *g == entity.generation).unwrap_or(false) && self.achetype.get(entity.index).copied().unwrap_or(false)
+        self.check(entity).is_ok()
     }
 
     /// Iterate over all valid [`Entity`]s.
     pub fn iter(&self) -> impl Iterator<Item = Entity> {
-        self.generations.iter().enumerate().filter_map(|(i, g)| if self.achetype.get(i).copied().unwrap_or(false) { Some(Entity { index: i, generation: *g }) } else { None })
+        self.generations.iter().enumerate().filter_map(|(i, g)| if self.achetype.get(i).is_some_and(|a| a.valid) { Some(Entity { index: i, generation: *g }) } else { None })
+    }
+
+    /// Get the valid [`Entity`] at the index.
+    fn at(&self, index: usize) -> Option<Entity> {
+        if self.achetype.get(index).is_some_and(|a| a.valid) { Some(Entity { index, generation: self.generations[index] }) } else { None }
+    }
+
+    /// Get the components an entity has.
+    ///
+    /// Returns an empty [`BitField`] if the entity is not valid.
+    pub fn components(&self, entity: Entity) -> BitField {
+        if !self.exists(entity) {
+            return 0;
+        }
+        self.achetype.get(entity.index).map(|a| a.components).unwrap_or(0)
+    }
+}
+
+/// How a [`Components`] lays out its data, chosen per type with [`Component::STORAGE`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StorageKind {
+    /// A slot for every entity index, fast to look up but as long as the highest entity index that had the component.
+    #[default]
+    Indexed,
+    /// A dense array of components and their entities, with a sparse array mapping entity indices into it.
+    /// Iterating only visits live components, so this suits components that few or short lived entities have.
+    SparseSet,
+    /// Only the ticks of the entities that have the component, for zero sized markers.
+    /// Nothing is allocated for the entities without it.
+    Marker,
+}
+
+/// A counter of frames used for detecting changes, advanced by [`World::maintain`].
+pub type Tick = u32;
+
+/// The ticks a component was added and last mutably accessed at.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ComponentTicks {
+    /// The tick the component was inserted at.
+    pub added: Tick,
+    /// The tick the component was last inserted or mutably accessed at.
+    pub changed: Tick,
+}
+
+impl ComponentTicks {
+    /// Whether the component was added at or after `tick`.
+    pub fn added_since(&self, tick: Tick) -> bool {
+        self.added >= tick
+    }
+
+    /// Whether the component was changed at or after `tick`.
+    pub fn changed_since(&self, tick: Tick) -> bool {
+        self.changed >= tick
     }
 }
 
+/// A component and its [`ComponentTicks`].
+struct Slot<T> {
+    value: T,
+    ticks: ComponentTicks,
+}
+
+/// The layout of a [`Components`].
+enum ComponentsData<T> {
+    /// Each index refers to an Entity at that index.
+    Indexed(Vec<Option<Slot<T>>>),
+    /// `sparse[entity.index]` is the position of the entity in `dense` and `entities`.
+    SparseSet { sparse: Vec<Option<usize>>, dense: Vec<Slot<T>>, entities: Vec<Entity> },
+    /// The slots keyed by entity index, a zero sized component only takes the space of its ticks.
+    Marker(BTreeMap<usize, Slot<T>>),
+}
+
 /// The Components type.
-/// Stores components data where each index refers to an Entity at that index.
+/// Stores components data in the layout given by [`StorageKind`].
 pub struct Components<T> {
-    data: Vec<Option<T>>,
+    data: ComponentsData<T>,
+    /// Entities that had this component removed this frame.
+    removed: Vec<Entity>,
+    /// The current tick of the [`World`], given to inserted and mutably accessed components.
+    change_tick: Tick,
 }
 
 impl<T> Components<T> {
+    /// Create an empty store with the given layout.
+    ///
+    /// Panics if the layout is [`StorageKind::Marker`] and `T` is not zero sized.
+    pub fn new(kind: StorageKind) -> Self {
+        let data = match kind {
+            StorageKind::Indexed => ComponentsData::Indexed(Vec::new()),
+            StorageKind::SparseSet => ComponentsData::SparseSet { sparse: Vec::new(), dense: Vec::new(), entities: Vec::new() },
+            StorageKind::Marker => {
+                assert!(size_of::<T>() == 0, "{} is not a zero sized marker", std::any::type_name::<T>());
+                ComponentsData::Marker(BTreeMap::new())
+            }
+        };
+        Components { data, removed: Vec::new(), change_tick: 0 }
+    }
+
+    /// The layout of this store.
+    pub fn kind(&self) -> StorageKind {
+        match self.data {
+            ComponentsData::Indexed(_) => StorageKind::Indexed,
+            ComponentsData::SparseSet { .. } => StorageKind::SparseSet,
+            ComponentsData::Marker(_) => StorageKind::Marker,
+        }
+    }
+
     /// Insert a component to the entity.
     ///
-    /// Returns [`Err(())`] if the entity is not valid.
-    /// In the future, we can use an actual error type for better user experience.
-    pub fn insert(&mut self, entities: &Entities, entity: Entity, component: T) -> Result<(), ()> {
-        if !entities.exists(entity) {
-            return Err(());
+    /// Does not update the entity's achetype, use [`World::insert`] instead.
+    /// Returns an [`EcsError`] if the entity is not valid.
+    fn insert(&mut self, entities: &Entities, entity: Entity, component: T) -> Result<(), EcsError> {
+        entities.check(entity)?;
+        let tick = self.change_tick;
+        // Replacing a component only counts as a change.
+        if let Some(slot) = self.get_ptr(entity.index) {
+            // SAFETY: `self` is borrowed mutably.
+            let slot = unsafe { &mut *slot };
+            slot.value = component;
+            slot.ticks.changed = tick;
+            return Ok(());
         }
-        // Allocate `None` for previous entities if they haven't been allocated yet.
-        while self.data.len() < entity.index + 1 {
-            self.data.push(None);
+        let slot = Slot { value: component, ticks: ComponentTicks { added: tick, changed: tick } };
+        match &mut self.data {
+            ComponentsData::Indexed(data) => {
+                // Allocate `None` for previous entities if they haven't been allocated yet.
+                if data.len() <= entity.index {
+                    data.resize_with(entity.index + 1, || None);
+                }
+                data[entity.index] = Some(slot);
+            }
+            ComponentsData::SparseSet { sparse, dense, entities } => {
+                if sparse.len() <= entity.index {
+                    sparse.resize(entity.index + 1, None);
+                }
+                sparse[entity.index] = Some(dense.len());
+                dense.push(slot);
+                entities.push(entity);
+            }
+            ComponentsData::Marker(data) => {
+                data.insert(entity.index, slot);
+            }
         }
-        *self.data.get_mut(entity.index).expect("Index should exists") = Some(component);
         Ok(())
     }
 
-    // fn remove(&mut self, entities: &Entities, entity: Entity) -> Result<(), ()> {
-    //     if !entities.exists(entity) {
-    //         return Err(());
-    //     }
-    //     if let Some(data) = self.data.get_mut(entity.index) {
-    //         *data = None;
-    //     }
-    //     Ok(())
-    // }
+    /// Remove the component from the entity and return it.
+    ///
+    /// The entity is recorded in [`Components::removed`].
+    /// Does not update the entity's achetype, use [`World::remove`] instead.
+    /// Returns an [`EcsError`] if the entity is not valid or if the entity does not have the component.
+    fn remove(&mut self, entities: &Entities, entity: Entity) -> Result<T, EcsError> {
+        entities.check(entity)?;
+        let component = self.take(entity.index).ok_or(EcsError::MissingComponent { entity, type_name: std::any::type_name::<T>() })?;
+        self.removed.push(entity);
+        Ok(component)
+    }
+
+    /// Take the component at the index regardless of the entity's generation.
+    fn take(&mut self, index: usize) -> Option<T> {
+        match &mut self.data {
+            ComponentsData::Indexed(data) => data.get_mut(index).and_then(Option::take).map(|slot| slot.value),
+            ComponentsData::SparseSet { sparse, dense, entities } => {
+                let position = sparse.get_mut(index)?.take()?;
+                // Move the last component into the hole so the arrays stay dense.
+                entities.swap_remove(position);
+                if let Some(moved) = entities.get(position) {
+                    sparse[moved.index] = Some(position);
+                }
+                Some(dense.swap_remove(position).value)
+            }
+            ComponentsData::Marker(data) => data.remove(&index).map(|slot| slot.value),
+        }
+    }
+
+    /// Iterate over entities that had this component removed this frame,
+    /// either by [`Components::remove`] or by [`World::despawn`].
+    ///
+    /// Despawned entities are no longer valid and can only be compared.
+    pub fn removed(&self) -> impl Iterator<Item = Entity> {
+        self.removed.iter().copied()
+    }
 
     /// Get the component from the entity.
     ///
-    /// Returns [`Err(())`] if the entity is not valid or if the entity does not have the component.
-    /// In the future, we can use an actual error type for better user experience.
-    pub fn get(&self, entities: &Entities, entity: Entity) -> Result<&T, ()> {
-        if !entities.exists(entity) {
-            return Err(());
+    /// Returns an [`EcsError`] if the entity is not valid or if the entity does not have the component.
+    pub fn get(&self, entities: &Entities, entity: Entity) -> Result<&T, EcsError> {
+        entities.check(entity)?;
+        self.get_at(entity.index).map(|slot| &slot.value).ok_or(EcsError::MissingComponent { entity, type_name: std::any::type_name::<T>() })
+    }
+
+    /// Get the component mutably from the entity, marking it as changed.
+    ///
+    /// Returns an [`EcsError`] if the entity is not valid or if the entity does not have the component.
+    pub fn get_mut(&mut self, entities: &Entities, entity: Entity) -> Result<&mut T, EcsError> {
+        entities.check(entity)?;
+        let tick = self.change_tick;
+        let slot = self.get_ptr(entity.index).ok_or(EcsError::MissingComponent { entity, type_name: std::any::type_name::<T>() })?;
+        // SAFETY: the pointer comes from `self`, which is borrowed mutably for the returned lifetime.
+        let slot = unsafe { &mut *slot };
+        slot.ticks.changed = tick;
+        Ok(&mut slot.value)
+    }
+
+    /// Get the ticks of the entity's component.
+    ///
+    /// Returns an [`EcsError`] if the entity is not valid or if the entity does not have the component.
+    pub fn ticks(&self, entities: &Entities, entity: Entity) -> Result<ComponentTicks, EcsError> {
+        entities.check(entity)?;
+        self.get_at(entity.index).map(|slot| slot.ticks).ok_or(EcsError::MissingComponent { entity, type_name: std::any::type_name::<T>() })
+    }
+
+    /// Iterate over every valid entity that has this component.
+    ///
+    /// A [`StorageKind::SparseSet`] only visits live components, a [`StorageKind::Indexed`] walks every slot.
+    pub fn iter<'a>(&'a self, entities: &'a Entities) -> impl Iterator<Item = (Entity, &'a T)> {
+        self.slots(entities).map(|(entity, slot)| (entity, &slot.value))
+    }
+
+    /// Iterate over the components added at or after `tick`.
+    ///
+    /// Use [`World::change_tick`] for the components added this frame.
+    pub fn added_since<'a>(&'a self, entities: &'a Entities, tick: Tick) -> impl Iterator<Item = (Entity, &'a T)> {
+        self.slots(entities).filter(move |(_, slot)| slot.ticks.added_since(tick)).map(|(entity, slot)| (entity, &slot.value))
+    }
+
+    /// Iterate over the components inserted or mutably accessed at or after `tick`.
+    ///
+    /// Use [`World::change_tick`] for the components changed this frame.
+    pub fn changed_since<'a>(&'a self, entities: &'a Entities, tick: Tick) -> impl Iterator<Item = (Entity, &'a T)> {
+        self.slots(entities).filter(move |(_, slot)| slot.ticks.changed_since(tick)).map(|(entity, slot)| (entity, &slot.value))
+    }
+
+    fn slots<'a>(&'a self, entities: &'a Entities) -> impl Iterator<Item = (Entity, &'a Slot<T>)> {
+        let (indexed, sparse, marker) = match &self.data {
+            ComponentsData::Indexed(data) => (Some(data.iter().enumerate().filter_map(|(index, c)| Some((entities.at(index)?, c.as_ref()?)))), None, None),
+            ComponentsData::SparseSet { dense, entities: owners, .. } => (None, Some(owners.iter().copied().zip(dense).filter(|(entity, _)| entities.exists(*entity))), None),
+            ComponentsData::Marker(data) => (None, None, Some(data.iter().filter_map(|(index, slot)| Some((entities.at(*index)?, slot))))),
+        };
+        indexed.into_iter().flatten().chain(sparse.into_iter().flatten()).chain(marker.into_iter().flatten())
+    }
+
+    /// The number of components stored, including those of entities despawned this frame.
+    pub fn len(&self) -> usize {
+        match &self.data {
+            ComponentsData::Indexed(data) => data.iter().filter(|c| c.is_some()).count(),
+            ComponentsData::SparseSet { dense, .. } => dense.len(),
+            ComponentsData::Marker(data) => data.len(),
+        }
+    }
+
+    /// Whether no components are stored.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the slot at the index regardless of the entity's generation.
+    fn get_at(&self, index: usize) -> Option<&Slot<T>> {
+        match &self.data {
+            ComponentsData::Indexed(data) => data.get(index)?.as_ref(),
+            ComponentsData::SparseSet { sparse, dense, .. } => dense.get((*sparse.get(index)?)?),
+            ComponentsData::Marker(data) => data.get(&index),
+        }
+    }
+
+    /// Get a pointer to the slot at the index regardless of the entity's generation.
+    ///
+    /// No reference to the other components is created, so pointers to
+    /// different indices may be dereferenced at the same time.
+    fn get_ptr(&mut self, index: usize) -> Option<*mut Slot<T>> {
+        match &mut self.data {
+            ComponentsData::Indexed(data) if index < data.len() => {
+                // SAFETY: the index is in bounds.
+                unsafe { (*data.as_mut_ptr().add(index)).as_mut().map(|c| c as *mut Slot<T>) }
+            }
+            ComponentsData::Indexed(_) => None,
+            ComponentsData::SparseSet { sparse, dense, .. } => {
+                let position = (*sparse.get(index)?)?;
+                // SAFETY: the sparse array only points inside the dense array.
+                Some(unsafe { dense.as_mut_ptr().add(position) })
+            }
+            ComponentsData::Marker(data) => data.get_mut(&index).map(|slot| slot as *mut Slot<T>),
         }
-        self.data.get(entity.index).and_then(|d| d.as_ref()).ok_or(())
     }
 
-    /// Get the component mutably from the entity.
+    /// The entities that have this component, if the store is a [`StorageKind::SparseSet`].
     ///
-    /// Returns [`Err(())`] if the entity is not valid or if the entity does not have the component.
-    /// In the future, we can use an actual error type for better user experience.
-    pub fn get_mut(&mut self, entities: &Entities, entity: Entity) -> Result<&mut T, ()> {
-        if !entities.exists(entity) {
-            return Err(());
+    /// May contain entities despawned this frame.
+    fn dense_entities(&self) -> Option<&[Entity]> {
+        match &self.data {
+            ComponentsData::Indexed(_) | ComponentsData::Marker(_) => None,
+            ComponentsData::SparseSet { entities, .. } => Some(entities),
+        }
+    }
+}
+
+/// A [`Components`] stored in a [`World`].
+struct Storage<T> {
+    /// The bit representing `T` in an [`EntityAchetype`].
+    bit: BitField,
+    components: RwLock<Components<T>>,
+}
+
+impl<T: Component> ComponentStorage for Storage<T> {
+    fn clear(&mut self, entity: Entity) {
+        self.components.get_mut().unwrap_or_else(PoisonError::into_inner).take(entity.index);
+    }
+
+    fn record_removed(&mut self, entity: Entity) {
+        let components = self.components.get_mut().unwrap_or_else(PoisonError::into_inner);
+        if components.get_at(entity.index).is_some() {
+            components.removed.push(entity);
         }
-        self.data.get_mut(entity.index).and_then(|d| d.as_mut()).ok_or(())
+    }
+
+    fn clear_removed(&mut self) {
+        self.components.get_mut().unwrap_or_else(PoisonError::into_inner).removed.clear();
+    }
+
+    fn set_change_tick(&mut self, tick: Tick) {
+        self.components.get_mut().unwrap_or_else(PoisonError::into_inner).change_tick = tick;
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
     }
 }
 
-impl<T> Default for Components<T> {
+impl<T: Component> Default for Components<T> {
     fn default() -> Self {
-        Components { data: Vec::default() }
+        Components::new(T::STORAGE)
     }
 }
 
-/// The events buffer is just a [`Vec<T>`] for now.
-pub type Events<T> = Vec<T>;
+/// Type erased access to the [`Components`] stored in a [`World`].
+pub trait ComponentStorage: Send + Sync {
+    /// Remove the component at the entity's index regardless of its generation.
+    fn clear(&mut self, entity: Entity);
+    /// Record the entity in [`Components::removed`] if it has this component, used when it is despawned.
+    fn record_removed(&mut self, entity: Entity);
+    /// Forget the entities that had this component removed.
+    fn clear_removed(&mut self);
+    /// Set the tick given to inserted and mutably accessed components.
+    fn set_change_tick(&mut self, tick: Tick);
+    /// Used for downcasting back to the concrete store.
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// A collection that knows every [`Components`] used in the game.
+pub trait ComponentRegistry {
+    /// Remove the entity's components from every stores.
+    fn remove_all(&mut self, entity: Entity);
+    /// Forget the removed entities of every stores.
+    fn clear_removed(&mut self);
+}
+
+impl ComponentRegistry for HashMap<TypeId, Box<dyn ComponentStorage>> {
+    fn remove_all(&mut self, entity: Entity) {
+        for storage in self.values_mut() {
+            storage.clear(entity);
+        }
+    }
+
+    fn clear_removed(&mut self) {
+        for storage in self.values_mut() {
+            storage.clear_removed();
+        }
+    }
+}
+
+/// The container of all entities, components and resources.
+///
+/// Component stores and resources are borrowed through a [`RwLock`] so a
+/// system can hold several of them at once using `&World`.
+/// Borrowing something that is already mutably borrowed panics instead of
+/// blocking.
+/// Spawning, despawning and inserting requires `&mut World`, or can be queued with [`World::commands`].
+#[derive(Default)]
+pub struct World {
+    entities: Entities,
+    /// Every [`Components`] keyed by their component type.
+    ///
+    /// Stores are boxed and never removed, this allows them to be created
+    /// lazily from `&World`.
+    components: RwLock<HashMap<TypeId, Box<dyn ComponentStorage>>>,
+    /// Every resources keyed by their type, each is a boxed [`RwLock<R>`].
+    resources: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    /// The current tick, see [`World::change_tick`].
+    change_tick: Tick,
+    /// The [`ComponentHooks`] keyed by the bit of their component type.
+    hooks: BTreeMap<BitField, ComponentHooks>,
+    /// Queued by [`Commands`] and applied by [`World::apply_commands`].
+    commands: Mutex<Vec<Command>>,
+}
+
+impl World {
+    /// Get the [`Entities`].
+    pub fn entities(&self) -> &Entities {
+        &self.entities
+    }
+
+    /// Create a new [`Entity`].
+    pub fn spawn(&mut self) -> Entity {
+        self.entities.spawn()
+    }
+
+    /// Despawn an existing [`Entity`].
+    ///
+    /// Its components are cleared on the next [`World::maintain`], their
+    /// `on_remove` hooks run and [`Components::removed`] records them right away. It is detached from its parent, its
+    /// children are left as is, see [`World::despawn_recursive`].
+    pub fn despawn(&mut self, entity: Entity) -> Result<(), EcsError> {
+        self.remove_parent(entity)?;
+        for on_remove in self.remove_hooks(entity) {
+            on_remove(self, entity);
+        }
+        for storage in self.components.get_mut().unwrap_or_else(PoisonError::into_inner).values_mut() {
+            storage.record_removed(entity);
+        }
+        self.entities.despawn(entity)
+    }
+
+    /// Clear components of despawned entities and allow their slots to be reused.
+    ///
+    /// Should be called once per frame.
+    pub fn maintain(&mut self) {
+        let components = self.components.get_mut().unwrap_or_else(PoisonError::into_inner);
+        self.entities.maintain(components);
+        self.change_tick += 1;
+        for storage in components.values_mut() {
+            storage.set_change_tick(self.change_tick);
+        }
+    }
+
+    /// The current tick, advanced by every [`World::maintain`].
+    ///
+    /// Components inserted or mutably accessed are stamped with it, see [`Components::changed_since`].
+    pub fn change_tick(&self) -> Tick {
+        self.change_tick
+    }
+
+    /// Insert a component to the entity, running its [`ComponentHooks`].
+    pub fn insert<T: Component>(&mut self, entity: Entity, component: T) -> Result<(), EcsError> {
+        let bit = self.component_bit::<T>();
+        let hooks = self.hooks(bit);
+        if let Some(on_replace) = hooks.on_replace
+            && self.entities.components(entity) & bit != 0
+        {
+            on_replace(self, entity);
+        }
+        self.insert_without_hooks(entity, component)?;
+        if let Some(on_insert) = hooks.on_insert {
+            on_insert(self, entity);
+        }
+        Ok(())
+    }
+
+    /// Insert a component to the entity without running its [`ComponentHooks`].
+    fn insert_without_hooks<T: Component>(&mut self, entity: Entity, component: T) -> Result<(), EcsError> {
+        let bit = self.component_bit::<T>();
+        self.storage::<T>().components.write().unwrap_or_else(PoisonError::into_inner).insert(&self.entities, entity, component)?;
+        self.entities.achetype[entity.index].components |= bit;
+        Ok(())
+    }
+
+    /// Remove a component from the entity and return it, running its [`ComponentHooks::on_remove`].
+    pub fn remove<T: Component>(&mut self, entity: Entity) -> Result<T, EcsError> {
+        let bit = self.component_bit::<T>();
+        if let Some(on_remove) = self.hooks(bit).on_remove
+            && self.entities.components(entity) & bit != 0
+        {
+            on_remove(self, entity);
+        }
+        let component = self.storage::<T>().components.write().unwrap_or_else(PoisonError::into_inner).remove(&self.entities, entity)?;
+        self.entities.achetype[entity.index].components &= !bit;
+        Ok(component)
+    }
+
+    /// Check if the entity has the component `T`.
+    pub fn has<T: Component>(&self, entity: Entity) -> bool {
+        self.entities.components(entity) & self.component_bit::<T>() != 0
+    }
+
+    /// Get the bit representing `T` in an [`EntityAchetype`].
+    pub fn component_bit<T: Component>(&self) -> BitField {
+        self.storage::<T>().bit
+    }
+
+    /// Get the store of `T`, creating it if it does not exist yet.
+    fn storage<T: Component>(&self) -> &Storage<T> {
+        let id = TypeId::of::<T>();
+        let storage: *const dyn ComponentStorage = {
+            let components = self.components.read().unwrap_or_else(PoisonError::into_inner);
+            match components.get(&id) {
+                Some(storage) => &**storage,
+                None => {
+                    drop(components);
+                    let mut components = self.components.write().unwrap_or_else(PoisonError::into_inner);
+                    let len = components.len();
+                    &**components.entry(id).or_insert_with(|| {
+                        assert!(len < BitField::BITS as usize, "Currently only supports {} components", BitField::BITS);
+                        let components = Components::<T> { change_tick: self.change_tick, ..Default::default() };
+                        Box::new(Storage { bit: 1 << len, components: RwLock::new(components) })
+                    })
+                }
+            }
+        };
+        // SAFETY: Stores are boxed and only ever added while the world is
+        // borrowed immutably, so the pointer stays valid for as long as `self`.
+        let storage = unsafe { &*storage };
+        storage.as_any().downcast_ref().expect("store is keyed by its type")
+    }
+
+    /// Borrow the [`Components`] of `T`.
+    ///
+    /// Panics if it is already mutably borrowed.
+    pub fn components<T: Component>(&self) -> RwLockReadGuard<'_, Components<T>> {
+        match self.storage::<T>().components.try_read() {
+            Ok(components) => components,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => panic!("Components<{}> is already mutably borrowed", std::any::type_name::<T>()),
+        }
+    }
+
+    /// Borrow the [`Components`] of `T` mutably.
+    ///
+    /// Panics if it is already borrowed.
+    pub fn components_mut<T: Component>(&self) -> RwLockWriteGuard<'_, Components<T>> {
+        match self.storage::<T>().components.try_write() {
+            Ok(components) => components,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => panic!("Components<{}> is already borrowed", std::any::type_name::<T>()),
+        }
+    }
+
+    /// Insert a resource, replacing the previous one of the same type.
+    pub fn insert_resource<R: Send + Sync + 'static>(&mut self, resource: R) {
+        self.resources.insert(TypeId::of::<R>(), Box::new(RwLock::new(resource)));
+    }
+
+    /// Check if the resource `R` exists.
+    pub fn contains_resource<R: Send + Sync + 'static>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<R>())
+    }
+
+    fn resource_lock<R: Send + Sync + 'static>(&self) -> &RwLock<R> {
+        let Some(resource) = self.resources.get(&TypeId::of::<R>()) else {
+            panic!("Resource {} does not exists", std::any::type_name::<R>());
+        };
+        resource.downcast_ref().expect("resource is keyed by its type")
+    }
+
+    /// Borrow the resource `R`.
+    ///
+    /// Panics if it does not exists or it is already mutably borrowed.
+    pub fn resource<R: Send + Sync + 'static>(&self) -> RwLockReadGuard<'_, R> {
+        match self.resource_lock::<R>().try_read() {
+            Ok(resource) => resource,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => panic!("Resource {} is already mutably borrowed", std::any::type_name::<R>()),
+        }
+    }
+
+    /// Borrow the resource `R` mutably.
+    ///
+    /// Panics if it does not exists or it is already borrowed.
+    pub fn resource_mut<R: Send + Sync + 'static>(&self) -> RwLockWriteGuard<'_, R> {
+        match self.resource_lock::<R>().try_write() {
+            Ok(resource) => resource,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => panic!("Resource {} is already borrowed", std::any::type_name::<R>()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Number(i32);
+    impl Component for Number {}
+
+    #[derive(Debug, PartialEq)]
+    struct Name(&'static str);
+    impl Component for Name {}
+
+    #[derive(Debug, PartialEq)]
+    struct Effect(u8);
+    impl Component for Effect {
+        const STORAGE: StorageKind = StorageKind::SparseSet;
+    }
+
+    #[test]
+    fn despawned_slot_is_not_reused_before_maintain() {
+        let mut world = World::default();
+        let entity = world.spawn();
+        world.despawn(entity).unwrap();
+
+        let next = world.spawn();
+        assert_ne!(entity.index, next.index);
+    }
+
+    #[test]
+    fn recycled_slot_starts_empty() {
+        let mut world = World::default();
+
+        let entity = world.spawn();
+        world.insert(entity, Number(10)).unwrap();
+        world.insert(entity, Name("enemy")).unwrap();
+        world.despawn(entity).unwrap();
+        world.maintain();
+
+        let recycled = world.spawn();
+        assert_eq!(entity.index, recycled.index);
+        assert_ne!(entity.generation, recycled.generation);
+        assert!(world.components::<Number>().get(world.entities(), recycled).is_err());
+        assert!(world.components::<Name>().get(world.entities(), recycled).is_err());
+    }
+
+    #[test]
+    fn errors_name_the_entity_and_component() {
+        let mut world = World::default();
+
+        let entity = world.spawn();
+        let type_name = std::any::type_name::<Number>();
+        assert_eq!(world.components::<Number>().get(world.entities(), entity), Err(EcsError::MissingComponent { entity, type_name }));
+        assert_eq!(world.components::<Number>().get(world.entities(), entity).unwrap_err().to_string(), format!("Entity 0v0 does not have the component {type_name}"));
+
+        world.despawn(entity).unwrap();
+        assert_eq!(world.despawn(entity), Err(EcsError::StaleGeneration { entity, current: 1 }));
+        assert_eq!(world.insert(entity, Number(1)), Err(EcsError::StaleGeneration { entity, current: 1 }));
+
+        let unknown = Entity { index: 10, generation: 0 };
+        assert_eq!(world.despawn(unknown), Err(EcsError::EntityNotFound(unknown)));
+    }
+
+    #[test]
+    fn remove_component_keeps_entity() {
+        let mut world = World::default();
+
+        let entity = world.spawn();
+        world.insert(entity, Number(5)).unwrap();
+        world.insert(entity, Name("enemy")).unwrap();
+
+        assert_eq!(world.remove::<Number>(entity), Ok(Number(5)));
+        assert!(world.entities().exists(entity));
+        assert!(world.components::<Number>().get(world.entities(), entity).is_err());
+        assert_eq!(world.components::<Name>().get(world.entities(), entity), Ok(&Name("enemy")));
+        assert!(matches!(world.remove::<Number>(entity), Err(EcsError::MissingComponent { .. })));
+    }
+
+    #[test]
+    fn sparse_set_stays_dense() {
+        let mut world = World::default();
+
+        let entities: Vec<Entity> = (0..4).map(|_| world.spawn()).collect();
+        for (i, entity) in entities.iter().enumerate().skip(1) {
+            world.insert(*entity, Effect(i as u8)).unwrap();
+        }
+        assert_eq!(world.components::<Effect>().kind(), StorageKind::SparseSet);
+
+        // Removing from the middle moves the last component into its place.
+        assert_eq!(world.remove::<Effect>(entities[1]), Ok(Effect(1)));
+        world.despawn(entities[2]).unwrap();
+        world.maintain();
+
+        let effects = world.components::<Effect>();
+        assert_eq!(effects.len(), 1);
+        assert_eq!(effects.iter(world.entities()).collect::<Vec<_>>(), vec![(entities[3], &Effect(3))]);
+        assert_eq!(effects.get(world.entities(), entities[3]), Ok(&Effect(3)));
+        assert!(effects.get(world.entities(), entities[0]).is_err());
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Flagged;
+    impl Component for Flagged {
+        const STORAGE: StorageKind = StorageKind::Marker;
+    }
+
+    #[test]
+    fn marker_only_tracks_entities_that_have_it() {
+        let mut world = World::default();
+
+        let entities: Vec<Entity> = (0..100).map(|_| world.spawn()).collect();
+        world.insert(entities[99], Flagged).unwrap();
+        world.insert(entities[99], Number(1)).unwrap();
+
+        let flagged = world.components::<Flagged>();
+        let ComponentsData::Marker(slots) = &flagged.data else { panic!("expected a marker store") };
+        assert_eq!(slots.len(), 1);
+        assert_eq!(flagged.get(world.entities(), entities[99]), Ok(&Flagged));
+        assert!(flagged.get(world.entities(), entities[0]).is_err());
+        drop(flagged);
+        // An indexed store has a slot up to the highest entity index.
+        let numbers = world.components::<Number>();
+        let ComponentsData::Indexed(slots) = &numbers.data else { panic!("expected an indexed store") };
+        assert_eq!(slots.len(), 100);
+        drop(numbers);
+
+        world.remove::<Flagged>(entities[99]).unwrap();
+        assert!(world.components::<Flagged>().is_empty());
+        assert_eq!(world.components::<Flagged>().removed().collect::<Vec<_>>(), vec![entities[99]]);
+    }
+
+    #[test]
+    fn sparse_set_skips_despawned_before_maintain() {
+        let mut world = World::default();
+
+        let kept = world.spawn();
+        let despawned = world.spawn();
+        world.insert(kept, Effect(1)).unwrap();
+        world.insert(despawned, Effect(2)).unwrap();
+        world.despawn(despawned).unwrap();
+
+        let effects = world.components::<Effect>();
+        assert_eq!(effects.iter(world.entities()).collect::<Vec<_>>(), vec![(kept, &Effect(1))]);
+    }
+
+    #[test]
+    fn change_ticks() {
+        let mut world = World::default();
+
+        let old = world.spawn();
+        let touched = world.spawn();
+        world.insert(old, Number(1)).unwrap();
+        world.insert(touched, Number(2)).unwrap();
+        world.maintain();
+
+        let tick = world.change_tick();
+        let new = world.spawn();
+        world.insert(new, Number(3)).unwrap();
+        world.components_mut::<Number>().get_mut(world.entities(), touched).unwrap().0 += 1;
+
+        let numbers = world.components::<Number>();
+        assert_eq!(numbers.added_since(world.entities(), tick).collect::<Vec<_>>(), vec![(new, &Number(3))]);
+        assert_eq!(numbers.changed_since(world.entities(), tick).collect::<Vec<_>>(), vec![(touched, &Number(3)), (new, &Number(3))]);
+        assert_eq!(numbers.ticks(world.entities(), touched), Ok(ComponentTicks { added: tick - 1, changed: tick }));
+    }
+
+    #[test]
+    fn removed_lasts_for_one_frame() {
+        let mut world = World::default();
+
+        let removed = world.spawn();
+        let despawned = world.spawn();
+        world.insert(removed, Number(1)).unwrap();
+        world.insert(despawned, Number(2)).unwrap();
+        world.insert(despawned, Name("enemy")).unwrap();
+        world.remove::<Number>(removed).unwrap();
+        world.despawn(despawned).unwrap();
+        assert_eq!(world.components::<Number>().removed().collect::<Vec<_>>(), vec![removed, despawned]);
+        assert_eq!(world.components::<Name>().removed().collect::<Vec<_>>(), vec![despawned]);
+
+        // Next frame
+        world.maintain();
+        assert_eq!(world.components::<Number>().removed().count(), 0);
+        assert_eq!(world.components::<Name>().removed().count(), 0);
+    }
+
+    #[test]
+    fn maintain_keeps_other_entities() {
+        let mut world = World::default();
+
+        let dead = world.spawn();
+        let alive = world.spawn();
+        world.insert(dead, Number(1)).unwrap();
+        world.insert(alive, Number(2)).unwrap();
+        world.despawn(dead).unwrap();
+        world.maintain();
+
+        assert_eq!(world.components::<Number>().get(world.entities(), alive), Ok(&Number(2)));
+    }
+
+    #[test]
+    fn achetype_tracks_components() {
+        let mut world = World::default();
+        let entity = world.spawn();
+        assert!(!world.has::<Number>(entity));
+
+        world.insert(entity, Number(1)).unwrap();
+        world.insert(entity, Name("enemy")).unwrap();
+        assert!(world.has::<Number>(entity));
+        assert_eq!(world.entities().components(entity), world.component_bit::<Number>() | world.component_bit::<Name>());
+
+        world.remove::<Number>(entity).unwrap();
+        assert!(!world.has::<Number>(entity));
+        assert!(world.has::<Name>(entity));
+
+        world.despawn(entity).unwrap();
+        world.maintain();
+        let recycled = world.spawn();
+        assert_eq!(world.entities().components(recycled), 0);
+    }
+
+    #[test]
+    fn resources() {
+        let mut world = World::default();
+        world.insert_resource(Number(0));
+        world.resource_mut::<Number>().0 += 1;
+        assert_eq!(*world.resource::<Number>(), Number(1));
+        assert!(!world.contains_resource::<Name>());
+    }
+
+    #[test]
+    #[should_panic(expected = "is already mutably borrowed")]
+    fn conflicting_borrow_panics() {
+        let world = World::default();
+        let _numbers = world.components_mut::<Number>();
+        let _numbers_again = world.components::<Number>();
+    }
+}
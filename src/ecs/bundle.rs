@@ -0,0 +1,257 @@
+//! Inserting groups of components in one step.
+
+use super::*;
+
+/// A group of components inserted together.
+///
+/// Tuples of bundles are bundles too.
+///
+/// ```ignore
+/// struct EnemyBundle {
+///     position: Vec2i32,
+///     health: Health,
+/// }
+///
+/// impl Bundle for EnemyBundle {
+///     fn build(self, builder: &mut EntityBuilder) {
+///         builder.with(self.position).with(self.health);
+///     }
+/// }
+/// ```
+pub trait Bundle: Send + 'static {
+    /// Add every component to the builder.
+    fn build(self, builder: &mut EntityBuilder);
+}
+
+/// Inserts a single component without running its hooks.
+type InsertFn = Box<dyn FnOnce(&mut World, Entity) -> Result<(), EcsError> + Send>;
+
+/// A single component waiting to be inserted.
+struct Insert {
+    /// Get the bit of the component, see [`World::component_bit`].
+    bit: fn(&World) -> BitField,
+    insert: InsertFn,
+}
+
+/// Components waiting to be inserted to an entity in one step.
+///
+/// ```ignore
+/// let mut builder = EntityBuilder::default();
+/// builder.with(position).bundle(EnemyBundle { .. });
+/// let enemy = builder.spawn(&mut world);
+/// ```
+#[derive(Default)]
+pub struct EntityBuilder {
+    inserts: Vec<Insert>,
+}
+
+impl EntityBuilder {
+    /// Add a component, replacing the one of the same type added before.
+    pub fn with<T: Component>(&mut self, component: T) -> &mut Self {
+        self.inserts.push(Insert { bit: World::component_bit::<T>, insert: Box::new(move |world, entity| world.insert_without_hooks(entity, component)) });
+        self
+    }
+
+    /// Add every component of the bundle.
+    pub fn bundle(&mut self, bundle: impl Bundle) -> &mut Self {
+        bundle.build(self);
+        self
+    }
+
+    /// Spawn an entity with every component added so far.
+    pub fn spawn(self, world: &mut World) -> Entity {
+        let entity = world.spawn();
+        self.insert(world, entity).expect("entity is just spawned");
+        entity
+    }
+
+    /// Insert every component added so far to the entity.
+    ///
+    /// The `on_replace` hooks run first, then every component is inserted,
+    /// then the `on_insert` hooks run, so hooks never see half of the
+    /// components. Nothing is inserted if the entity does not exist, or if an
+    /// `on_replace` hook despawned it.
+    pub fn insert(self, world: &mut World, entity: Entity) -> Result<(), EcsError> {
+        world.entities().check(entity)?;
+        // Each hook runs once even if its component was added twice.
+        let mut bits: Vec<BitField> = Vec::new();
+        for insert in &self.inserts {
+            let bit = (insert.bit)(world);
+            if !bits.contains(&bit) {
+                bits.push(bit);
+            }
+        }
+
+        let replaced = world.entities().components(entity);
+        for bit in bits.iter().filter(|bit| replaced & **bit != 0) {
+            if let Some(on_replace) = world.hooks(*bit).on_replace {
+                on_replace(world, entity);
+            }
+        }
+        world.entities().check(entity)?;
+        for insert in self.inserts {
+            (insert.insert)(world, entity)?;
+        }
+        for bit in bits {
+            // A hook may have despawned the entity, the rest have nothing left to react to.
+            if !world.entities().exists(entity) {
+                break;
+            }
+            if let Some(on_insert) = world.hooks(bit).on_insert {
+                on_insert(world, entity);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Bundle for EntityBuilder {
+    fn build(self, builder: &mut EntityBuilder) {
+        builder.inserts.extend(self.inserts);
+    }
+}
+
+macro_rules! bundle_tuple_impl {
+    ($($B:ident),*) => {
+        impl<$($B: Bundle),*> Bundle for ($($B,)*) {
+            #[allow(non_snake_case)]
+            fn build(self, builder: &mut EntityBuilder) {
+                let ($($B,)*) = self;
+                $($B.build(builder);)*
+            }
+        }
+    };
+}
+
+bundle_tuple_impl!(A);
+bundle_tuple_impl!(A, B);
+bundle_tuple_impl!(A, B, C);
+bundle_tuple_impl!(A, B, C, D);
+
+impl World {
+    /// Spawn an entity with every component of the bundle.
+    pub fn spawn_bundle(&mut self, bundle: impl Bundle) -> Entity {
+        let mut builder = EntityBuilder::default();
+        builder.bundle(bundle);
+        builder.spawn(self)
+    }
+
+    /// Insert every component of the bundle to the entity.
+    ///
+    /// Nothing is inserted if the entity does not exist.
+    pub fn insert_bundle(&mut self, entity: Entity, bundle: impl Bundle) -> Result<(), EcsError> {
+        let mut builder = EntityBuilder::default();
+        builder.bundle(bundle);
+        builder.insert(self, entity)
+    }
+}
+
+impl<'w> Commands<'w> {
+    /// Reserve a new [`Entity`] that is spawned with every component of the bundle.
+    pub fn spawn_bundle(&mut self, bundle: impl Bundle) -> EntityCommands<'_, 'w> {
+        let mut entity = self.spawn();
+        entity.insert_bundle(bundle);
+        entity
+    }
+
+    /// Queue [`World::insert_bundle`].
+    pub fn insert_bundle(&mut self, entity: Entity, bundle: impl Bundle) {
+        self.add(move |world| {
+            let _ = world.insert_bundle(entity, bundle);
+        });
+    }
+}
+
+impl EntityCommands<'_, '_> {
+    /// Queue inserting every component of the bundle to the entity.
+    pub fn insert_bundle(&mut self, bundle: impl Bundle) -> &mut Self {
+        let entity = self.id();
+        self.commands().insert_bundle(entity, bundle);
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    impl Component for Position {}
+
+    #[derive(Debug, PartialEq)]
+    struct Health(i32);
+    impl Component for Health {}
+
+    #[derive(Debug, PartialEq)]
+    struct Name(&'static str);
+    impl Component for Name {}
+
+    struct UnitBundle {
+        position: Position,
+        health: Health,
+    }
+
+    impl Bundle for UnitBundle {
+        fn build(self, builder: &mut EntityBuilder) {
+            builder.with(self.position).with(self.health);
+        }
+    }
+
+    #[test]
+    fn spawn_bundle_inserts_every_component() {
+        let mut world = World::default();
+        let mut name = EntityBuilder::default();
+        name.with(Name("boss")).with(Health(1));
+        let boss = world.spawn_bundle((UnitBundle { position: Position(3), health: Health(20) }, name));
+
+        // Later components replace the earlier ones.
+        assert_eq!(world.query::<(&Position, &Health, &Name)>().get(boss), Ok((&Position(3), &Health(1), &Name("boss"))));
+    }
+
+    #[test]
+    fn insert_bundle_on_missing_entity_inserts_nothing() {
+        let mut world = World::default();
+        let dead = world.spawn();
+        world.despawn(dead).unwrap();
+        world.maintain();
+
+        assert!(world.insert_bundle(dead, UnitBundle { position: Position(0), health: Health(1) }).is_err());
+        assert!(world.components::<Position>().is_empty());
+        assert!(world.components::<Health>().is_empty());
+    }
+
+    #[test]
+    fn commands_spawn_bundle() {
+        let mut world = World::default();
+        let unit = world.commands().spawn_bundle(UnitBundle { position: Position(1), health: Health(5) }).id();
+        assert!(!world.entities().exists(unit));
+
+        world.apply_commands();
+        assert_eq!(world.query::<(&Position, &Health)>().get(unit), Ok((&Position(1), &Health(5))));
+    }
+
+    #[test]
+    fn hooks_run_once_the_whole_bundle_is_inserted() {
+        let mut world = World::default();
+        // Despawning in a hook used to leave the rest of the bundle to insert.
+        world.hooks_mut::<Position>().on_insert = Some(|world, entity| {
+            assert!(world.has::<Health>(entity));
+            world.despawn(entity).unwrap();
+        });
+        let unit = world.spawn_bundle(UnitBundle { position: Position(1), health: Health(5) });
+
+        assert!(!world.entities().exists(unit));
+    }
+
+    #[test]
+    fn despawning_on_replace_inserts_nothing() {
+        let mut world = World::default();
+        world.hooks_mut::<Health>().on_replace = Some(|world, entity| world.despawn(entity).unwrap());
+        let unit = world.spawn();
+        world.insert(unit, Health(5)).unwrap();
+
+        assert!(world.insert_bundle(unit, UnitBundle { position: Position(1), health: Health(1) }).is_err());
+        assert!(world.components::<Position>().is_empty());
+    }
+}
@@ -0,0 +1,198 @@
+//! Deferring structural changes to the [`World`].
+
+use super::*;
+
+/// A deferred operation on the [`World`].
+pub type Command = Box<dyn FnOnce(&mut World) + Send>;
+
+/// A queue of [`Command`]s that only needs `&World`.
+///
+/// Commands are applied in order by [`World::apply_commands`]. Commands
+/// targeting an entity that no longer exists by then are ignored.
+///
+/// ```ignore
+/// let mut commands = world.commands();
+/// let id = commands.spawn().insert(position).insert(Health(10)).id();
+/// commands.entity(parent).insert(Target(id));
+/// ```
+pub struct Commands<'w> {
+    world: &'w World,
+}
+
+impl<'w> Commands<'w> {
+    /// Queue an arbitrary operation.
+    pub fn add(&mut self, command: impl FnOnce(&mut World) + Send + 'static) {
+        self.world.commands.lock().unwrap_or_else(PoisonError::into_inner).push(Box::new(command));
+    }
+
+    /// Reserve a new [`Entity`] that later commands can refer to.
+    ///
+    /// The entity exists once the commands are applied.
+    pub fn spawn(&mut self) -> EntityCommands<'_, 'w> {
+        let entity = self.world.entities.reserve();
+        EntityCommands { commands: self, entity }
+    }
+
+    /// Queue commands for an existing [`Entity`].
+    pub fn entity(&mut self, entity: Entity) -> EntityCommands<'_, 'w> {
+        EntityCommands { commands: self, entity }
+    }
+
+    /// Queue [`World::insert`].
+    pub fn insert<T: Component>(&mut self, entity: Entity, component: T) {
+        self.add(move |world| {
+            let _ = world.insert(entity, component);
+        });
+    }
+
+    /// Queue [`World::remove`].
+    pub fn remove<T: Component>(&mut self, entity: Entity) {
+        self.add(move |world| {
+            let _ = world.remove::<T>(entity);
+        });
+    }
+
+    /// Queue [`World::despawn`].
+    pub fn despawn(&mut self, entity: Entity) {
+        self.add(move |world| {
+            let _ = world.despawn(entity);
+        });
+    }
+}
+
+impl SystemParam for Commands<'_> {
+    type State = ();
+    type Item<'w, 's> = Commands<'w>;
+
+    fn access(access: &mut Access) {
+        access.add_commands();
+    }
+
+    fn fetch<'w>(world: &'w World, _state: &mut ()) -> Commands<'w> {
+        world.commands()
+    }
+}
+
+/// [`Commands`] for a single [`Entity`].
+pub struct EntityCommands<'c, 'w> {
+    commands: &'c mut Commands<'w>,
+    entity: Entity,
+}
+
+impl<'w> EntityCommands<'_, 'w> {
+    /// The entity the commands are for.
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    /// The [`Commands`] these commands are queued to.
+    pub fn commands(&mut self) -> &mut Commands<'w> {
+        self.commands
+    }
+
+    /// Queue inserting a component to the entity.
+    pub fn insert<T: Component>(&mut self, component: T) -> &mut Self {
+        self.commands.insert(self.entity, component);
+        self
+    }
+
+    /// Queue removing a component from the entity.
+    pub fn remove<T: Component>(&mut self) -> &mut Self {
+        self.commands.remove::<T>(self.entity);
+        self
+    }
+
+    /// Queue despawning the entity.
+    pub fn despawn(&mut self) {
+        self.commands.despawn(self.entity);
+    }
+}
+
+impl World {
+    /// Get a [`Commands`] for queueing changes to the world.
+    pub fn commands(&self) -> Commands<'_> {
+        Commands { world: self }
+    }
+
+    /// Spawn the reserved entities and apply every queued [`Command`].
+    ///
+    /// This is a sync point, commands queued while applying are applied as well.
+    pub fn apply_commands(&mut self) {
+        self.entities.flush();
+        loop {
+            let queue = std::mem::take(self.commands.get_mut().unwrap_or_else(PoisonError::into_inner));
+            if queue.is_empty() {
+                break;
+            }
+            for command in queue {
+                command(self);
+            }
+            self.entities.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Health(i32);
+    impl Component for Health {}
+
+    #[derive(Debug, PartialEq)]
+    struct Target(Entity);
+    impl Component for Target {}
+
+    #[test]
+    fn reserved_entity_can_be_referenced() {
+        let mut world = World::default();
+        let hunter = world.spawn();
+
+        let mut commands = world.commands();
+        let prey = commands.spawn().insert(Health(3)).id();
+        commands.entity(hunter).insert(Target(prey));
+        assert!(!world.entities().exists(prey));
+
+        world.apply_commands();
+        assert!(world.entities().exists(prey));
+        assert_eq!(world.query::<&Health>().get(prey), Ok(&Health(3)));
+        assert_eq!(world.query::<&Target>().get(hunter), Ok(&Target(prey)));
+    }
+
+    #[test]
+    fn reserve_reuses_free_slots() {
+        let mut world = World::default();
+        let dead = world.spawn();
+        world.despawn(dead).unwrap();
+        world.maintain();
+
+        let mut commands = world.commands();
+        let reused = commands.spawn().id();
+        let new = commands.spawn().id();
+        world.apply_commands();
+
+        assert_eq!(reused.index, dead.index);
+        assert_ne!(reused, dead);
+        assert_ne!(new.index, dead.index);
+        assert_eq!(world.entities().iter().collect::<Vec<_>>(), vec![reused, new]);
+        assert_ne!(world.spawn().index, new.index);
+    }
+
+    #[test]
+    fn commands_apply_in_order() {
+        let mut world = World::default();
+        let entity = world.spawn();
+        world.insert(entity, Health(1)).unwrap();
+
+        let mut commands = world.commands();
+        commands.entity(entity).remove::<Health>().insert(Health(2));
+        commands.despawn(entity);
+        commands.insert(entity, Health(3));
+        world.apply_commands();
+
+        assert!(!world.entities().exists(entity));
+        // Removed once by `remove` and once more by `despawn`, after being inserted again.
+        assert_eq!(world.components::<Health>().removed().collect::<Vec<_>>(), vec![entity, entity]);
+    }
+}
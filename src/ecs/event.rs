@@ -0,0 +1,196 @@
+//! Sending data between systems.
+
+use super::*;
+use std::marker::PhantomData;
+
+/// A double-buffered queue of events, stored as a resource.
+///
+/// Events live for two [`Events::update`]s so a reader running once per frame
+/// sees every event exactly once, no matter if it runs before or after the
+/// sender in the frame.
+pub struct Events<T> {
+    /// Events sent before the last update.
+    previous: Vec<T>,
+    /// Events sent since the last update.
+    current: Vec<T>,
+    /// The id of the first event in `previous`.
+    ///
+    /// Each event gets an increasing id, which is what [`EventCursor`] tracks.
+    start: usize,
+}
+
+impl<T> Events<T> {
+    /// Send an event.
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+    }
+
+    /// Drop the events sent before the last update and swap the buffers.
+    ///
+    /// Should be called once per frame.
+    pub fn update(&mut self) {
+        self.start += self.previous.len();
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    /// Iterate over every buffered event.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.previous.iter().chain(self.current.iter())
+    }
+
+    /// The amount of buffered events.
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    /// Whether there are no buffered events.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over the events not yet seen by `cursor`, marking them as read.
+    ///
+    /// Events dropped by [`Events::update`] before being read are skipped.
+    pub fn read<'a>(&'a self, cursor: &mut EventCursor<T>) -> impl Iterator<Item = &'a T> + use<'a, T> {
+        let skip = cursor.next.saturating_sub(self.start);
+        cursor.next = self.start + self.len();
+        self.iter().skip(skip)
+    }
+
+    /// The amount of events not yet seen by `cursor`.
+    pub fn unread(&self, cursor: &EventCursor<T>) -> usize {
+        (self.start + self.len()).saturating_sub(cursor.next.max(self.start))
+    }
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Events { previous: Vec::new(), current: Vec::new(), start: 0 }
+    }
+}
+
+/// The position of a reader in an [`Events`].
+///
+/// Each reader keeps its own cursor between frames.
+pub struct EventCursor<T> {
+    /// The id of the next event to read.
+    next: usize,
+    _events: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for EventCursor<T> {
+    fn default() -> Self {
+        EventCursor { next: 0, _events: PhantomData }
+    }
+}
+
+/// A [`SystemParam`] reading the events the system has not seen yet.
+///
+/// The [`EventCursor`] is kept by the system between runs.
+pub struct EventReader<'w, 's, T: Send + Sync + 'static> {
+    last_read: &'s mut EventCursor<T>,
+    events: Res<'w, Events<T>>,
+}
+
+impl<T: Send + Sync + 'static> EventReader<'_, '_, T> {
+    /// Iterate over the unread events, marking them as read.
+    pub fn read(&mut self) -> impl Iterator<Item = &T> {
+        self.events.read(self.last_read)
+    }
+
+    /// The amount of unread events.
+    pub fn len(&self) -> usize {
+        self.events.unread(self.last_read)
+    }
+
+    /// Whether there are no unread events.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParam for EventReader<'_, '_, T> {
+    type State = EventCursor<T>;
+    type Item<'w, 's> = EventReader<'w, 's, T>;
+
+    fn access(access: &mut Access) {
+        access.add_resource_read::<Events<T>>();
+    }
+
+    fn fetch<'w, 's>(world: &'w World, state: &'s mut EventCursor<T>) -> EventReader<'w, 's, T> {
+        EventReader { last_read: state, events: Res::fetch(world, &mut ()) }
+    }
+}
+
+/// A [`SystemParam`] sending events.
+pub struct EventWriter<'w, T: Send + Sync + 'static> {
+    events: ResMut<'w, Events<T>>,
+}
+
+impl<T: Send + Sync + 'static> EventWriter<'_, T> {
+    /// Send an event.
+    pub fn write(&mut self, event: T) {
+        self.events.send(event);
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParam for EventWriter<'_, T> {
+    type State = ();
+    type Item<'w, 's> = EventWriter<'w, T>;
+
+    fn access(access: &mut Access) {
+        access.add_resource_write::<Events<T>>();
+    }
+
+    fn fetch<'w>(world: &'w World, _state: &mut ()) -> EventWriter<'w, T> {
+        EventWriter { events: ResMut::fetch(world, &mut ()) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn events_last_two_updates() {
+        let mut events = Events::default();
+        events.send(1);
+        events.update();
+        events.send(2);
+        assert_eq!(events.iter().collect::<Vec<_>>(), vec![&1, &2]);
+        events.update();
+        assert_eq!(events.iter().collect::<Vec<_>>(), vec![&2]);
+        events.update();
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn readers_see_each_event_once() {
+        let mut events = Events::default();
+        let mut early = EventCursor::default();
+        let mut late = EventCursor::default();
+
+        // The early reader runs before the event is sent in the frame.
+        assert_eq!(events.read(&mut early).count(), 0);
+        events.send("kill");
+        assert_eq!(events.read(&mut late).collect::<Vec<_>>(), vec![&"kill"]);
+
+        events.update();
+        assert_eq!(events.unread(&early), 1);
+        assert_eq!(events.read(&mut early).collect::<Vec<_>>(), vec![&"kill"]);
+        assert_eq!(events.unread(&late), 0);
+        assert_eq!(events.read(&mut late).count(), 0);
+    }
+
+    #[test]
+    fn lagging_reader_skips_dropped_events() {
+        let mut events = Events::default();
+        let mut cursor = EventCursor::default();
+        events.send(1);
+        events.update();
+        events.update();
+        events.send(2);
+        assert_eq!(events.unread(&cursor), 1);
+        assert_eq!(events.read(&mut cursor).collect::<Vec<_>>(), vec![&2]);
+    }
+}
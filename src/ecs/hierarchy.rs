@@ -0,0 +1,216 @@
+//! Parent and child relations between entities.
+
+use super::*;
+
+/// A component pointing to the parent of an entity.
+///
+/// Kept in sync with [`Children`] by [`World::set_parent`], [`World::remove_parent`] and [`World::despawn`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parent(Entity);
+
+impl Parent {
+    /// The parent entity.
+    pub fn get(&self) -> Entity {
+        self.0
+    }
+}
+
+impl Component for Parent {}
+
+/// A component for the children of an entity, in the order they were added.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Children(Vec<Entity>);
+
+impl Children {
+    /// Iterate over the children.
+    pub fn iter(&self) -> impl Iterator<Item = Entity> {
+        self.0.iter().copied()
+    }
+
+    /// The amount of children.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether there are no children.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Component for Children {}
+
+impl World {
+    /// Make `entity` a child of `parent`, detaching it from its previous parent.
+    pub fn set_parent(&mut self, entity: Entity, parent: Entity) -> Result<(), EcsError> {
+        self.entities.check(entity)?;
+        self.entities.check(parent)?;
+        if parent == entity || self.ancestors(parent).contains(&entity) {
+            return Err(EcsError::HierarchyCycle { entity, parent });
+        }
+        self.remove_parent(entity)?;
+        self.insert(entity, Parent(parent))?;
+        let mut children = self.components_mut::<Children>();
+        if let Ok(children) = children.get_mut(&self.entities, parent) {
+            children.0.push(entity);
+            return Ok(());
+        }
+        drop(children);
+        self.insert(parent, Children(vec![entity]))
+    }
+
+    /// Detach `entity` from its parent and return the parent, if it had one.
+    pub fn remove_parent(&mut self, entity: Entity) -> Result<Option<Entity>, EcsError> {
+        self.entities.check(entity)?;
+        let Ok(Parent(parent)) = self.remove::<Parent>(entity) else { return Ok(None) };
+        // The parent may have been despawned without its children.
+        if let Ok(children) = self.components_mut::<Children>().get_mut(&self.entities, parent) {
+            children.0.retain(|child| *child != entity);
+        }
+        Ok(Some(parent))
+    }
+
+    /// The parent of the entity, its parent and so on.
+    pub fn ancestors(&self, entity: Entity) -> Vec<Entity> {
+        let parents = self.components::<Parent>();
+        let mut ancestors = Vec::new();
+        let mut current = entity;
+        while let Ok(Parent(parent)) = parents.get(&self.entities, current) {
+            ancestors.push(*parent);
+            current = *parent;
+        }
+        ancestors
+    }
+
+    /// Every child of the entity, their children and so on, depth first.
+    pub fn descendants(&self, entity: Entity) -> Vec<Entity> {
+        let children = self.components::<Children>();
+        let mut descendants = Vec::new();
+        let mut stack = vec![entity];
+        while let Some(current) = stack.pop() {
+            if current != entity {
+                descendants.push(current);
+            }
+            let Ok(Children(current)) = children.get(&self.entities, current) else { continue };
+            // Reversed so the first child is visited first.
+            stack.extend(current.iter().rev().filter(|child| self.entities.exists(**child)));
+        }
+        descendants
+    }
+
+    /// Despawn the entity and every of its descendants, detaching it from its parent.
+    pub fn despawn_recursive(&mut self, entity: Entity) -> Result<(), EcsError> {
+        self.entities.check(entity)?;
+        for descendant in self.descendants(entity) {
+            self.despawn(descendant)?;
+        }
+        self.despawn(entity)
+    }
+}
+
+impl Commands<'_> {
+    /// Queue [`World::set_parent`].
+    pub fn set_parent(&mut self, entity: Entity, parent: Entity) {
+        self.add(move |world| {
+            let _ = world.set_parent(entity, parent);
+        });
+    }
+
+    /// Queue [`World::despawn_recursive`].
+    pub fn despawn_recursive(&mut self, entity: Entity) {
+        self.add(move |world| {
+            let _ = world.despawn_recursive(entity);
+        });
+    }
+}
+
+impl EntityCommands<'_, '_> {
+    /// Queue making the entity a child of `parent`.
+    pub fn set_parent(&mut self, parent: Entity) -> &mut Self {
+        let entity = self.id();
+        self.commands().set_parent(entity, parent);
+        self
+    }
+
+    /// Queue despawning the entity and its descendants.
+    pub fn despawn_recursive(&mut self) {
+        let entity = self.id();
+        self.commands().despawn_recursive(entity);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn set_parent_moves_between_children() {
+        let mut world = World::default();
+        let first = world.spawn();
+        let second = world.spawn();
+        let child = world.spawn();
+
+        world.set_parent(child, first).unwrap();
+        assert_eq!(world.components::<Children>().get(world.entities(), first).unwrap().iter().collect::<Vec<_>>(), vec![child]);
+        world.set_parent(child, second).unwrap();
+        assert!(world.components::<Children>().get(world.entities(), first).unwrap().is_empty());
+        assert_eq!(world.components::<Parent>().get(world.entities(), child).unwrap().get(), second);
+        assert_eq!(world.remove_parent(child), Ok(Some(second)));
+        assert_eq!(world.remove_parent(child), Ok(None));
+        assert!(world.components::<Children>().get(world.entities(), second).unwrap().is_empty());
+    }
+
+    #[test]
+    fn parenting_a_descendant_fails() {
+        let mut world = World::default();
+        let root = world.spawn();
+        let child = world.spawn();
+        let grandchild = world.spawn();
+        world.set_parent(child, root).unwrap();
+        world.set_parent(grandchild, child).unwrap();
+
+        assert_eq!(world.set_parent(root, grandchild), Err(EcsError::HierarchyCycle { entity: root, parent: grandchild }));
+        assert_eq!(world.set_parent(root, root), Err(EcsError::HierarchyCycle { entity: root, parent: root }));
+        assert_eq!(world.ancestors(grandchild), vec![child, root]);
+    }
+
+    #[test]
+    fn despawn_recursive_removes_descendants() {
+        let mut world = World::default();
+        let root = world.spawn();
+        let boss = world.spawn();
+        let arm = world.spawn();
+        let hand = world.spawn();
+        let leg = world.spawn();
+        world.set_parent(boss, root).unwrap();
+        world.set_parent(arm, boss).unwrap();
+        world.set_parent(hand, arm).unwrap();
+        world.set_parent(leg, boss).unwrap();
+        assert_eq!(world.descendants(boss), vec![arm, hand, leg]);
+
+        world.commands().entity(boss).despawn_recursive();
+        world.apply_commands();
+        assert_eq!(world.entities().iter().collect::<Vec<_>>(), vec![root]);
+        assert!(world.components::<Children>().get(world.entities(), root).unwrap().is_empty());
+    }
+
+    #[test]
+    fn despawned_child_is_removed_from_children() {
+        let mut world = World::default();
+        let parent = world.spawn();
+        let first = world.spawn();
+        let second = world.spawn();
+        world.set_parent(first, parent).unwrap();
+        world.set_parent(second, parent).unwrap();
+
+        world.despawn(first).unwrap();
+        world.maintain();
+        let recycled = world.spawn();
+        assert_eq!(recycled.index, first.index);
+        assert_eq!(world.components::<Children>().get(world.entities(), parent).unwrap().iter().collect::<Vec<_>>(), vec![second]);
+        assert_eq!(world.descendants(parent), vec![second]);
+
+        world.despawn_recursive(parent).unwrap();
+        assert!(world.entities().exists(recycled));
+    }
+}
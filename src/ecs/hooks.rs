@@ -0,0 +1,100 @@
+//! Reacting to components being inserted, replaced or removed.
+
+use super::*;
+
+/// A function called with the [`World`] and the entity whose component changed.
+pub type ComponentHook = fn(&mut World, Entity);
+
+/// The hooks of a component type, see [`World::hooks_mut`].
+///
+/// Hooks only run for [`World::insert`], [`World::remove`] and
+/// [`World::despawn`], including when they are queued by [`Commands`].
+/// Mutating a component through a [`Query`] or [`Components::get_mut`] does
+/// not run any hook.
+#[derive(Clone, Copy, Default)]
+pub struct ComponentHooks {
+    /// Called after the component is inserted, including when it replaces another.
+    pub on_insert: Option<ComponentHook>,
+    /// Called before the component is replaced, while the old one can still be read.
+    pub on_replace: Option<ComponentHook>,
+    /// Called before the component is removed or its entity despawned, while it can still be read.
+    pub on_remove: Option<ComponentHook>,
+}
+
+impl World {
+    /// Get the hooks of the component `T` to set them.
+    ///
+    /// ```ignore
+    /// world.hooks_mut::<Collider>().on_remove = Some(|world, entity| {
+    ///     // ...
+    /// });
+    /// ```
+    pub fn hooks_mut<T: Component>(&mut self) -> &mut ComponentHooks {
+        let bit = self.component_bit::<T>();
+        self.hooks.entry(bit).or_default()
+    }
+
+    /// Get the hooks of the component with the bit.
+    pub(super) fn hooks(&self, bit: BitField) -> ComponentHooks {
+        self.hooks.get(&bit).copied().unwrap_or_default()
+    }
+
+    /// Get the `on_remove` hooks of every component the entity has.
+    pub(super) fn remove_hooks(&self, entity: Entity) -> Vec<ComponentHook> {
+        let components = self.entities.components(entity);
+        self.hooks.iter().filter(|(bit, _)| components & **bit != 0).filter_map(|(_, hooks)| hooks.on_remove).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Position(i32);
+    impl Component for Position {}
+
+    /// Every hook called, with the position the entity had at that time.
+    #[derive(Default)]
+    struct Log(Vec<(&'static str, Option<i32>)>);
+
+    fn log(world: &mut World, entity: Entity, hook: &'static str) {
+        let position = world.components::<Position>().get(world.entities(), entity).ok().map(|position| position.0);
+        world.resource_mut::<Log>().0.push((hook, position));
+    }
+
+    fn world_with_hooks() -> World {
+        let mut world = World::default();
+        world.insert_resource(Log::default());
+        let hooks = world.hooks_mut::<Position>();
+        hooks.on_insert = Some(|world, entity| log(world, entity, "insert"));
+        hooks.on_replace = Some(|world, entity| log(world, entity, "replace"));
+        hooks.on_remove = Some(|world, entity| log(world, entity, "remove"));
+        world
+    }
+
+    #[test]
+    fn hooks_see_the_old_and_new_component() {
+        let mut world = world_with_hooks();
+        let entity = world.spawn();
+        world.insert(entity, Position(1)).unwrap();
+        world.insert(entity, Position(2)).unwrap();
+        world.remove::<Position>(entity).unwrap();
+        assert!(world.remove::<Position>(entity).is_err());
+
+        assert_eq!(world.resource::<Log>().0, vec![("insert", Some(1)), ("replace", Some(1)), ("insert", Some(2)), ("remove", Some(2))]);
+    }
+
+    #[test]
+    fn despawn_runs_remove_hooks() {
+        let mut world = world_with_hooks();
+        let entity = world.spawn();
+        let empty = world.spawn();
+        world.commands().insert(entity, Position(3));
+        world.commands().despawn(entity);
+        world.commands().despawn(empty);
+        world.apply_commands();
+
+        assert_eq!(world.resource::<Log>().0, vec![("insert", Some(3)), ("remove", Some(3))]);
+    }
+}
@@ -0,0 +1,725 @@
+//! Iterating over entities with multiple components.
+
+use super::*;
+use std::marker::PhantomData;
+
+/// The components and resources a query or a system reads and writes.
+///
+/// Used for detecting conflicting borrows.
+#[derive(Default, Clone, Debug)]
+pub struct Access {
+    reads: HashMap<TypeId, &'static str>,
+    writes: HashMap<TypeId, &'static str>,
+    resource_reads: HashMap<TypeId, &'static str>,
+    resource_writes: HashMap<TypeId, &'static str>,
+    /// Whether [`Commands`] are queued, their order is only deterministic if
+    /// one of the borrowers queues at a time.
+    commands: bool,
+}
+
+impl Access {
+    /// Declare a read of the component `T`.
+    ///
+    /// Panics if `T` is already written.
+    pub fn add_read<T: 'static>(&mut self) {
+        add_read(&mut self.reads, &self.writes, TypeId::of::<T>(), std::any::type_name::<T>());
+    }
+
+    /// Declare a write of the component `T`.
+    ///
+    /// Panics if `T` is already read or written.
+    pub fn add_write<T: 'static>(&mut self) {
+        add_write(&self.reads, &mut self.writes, TypeId::of::<T>(), std::any::type_name::<T>());
+    }
+
+    /// Declare a read of the resource `R`.
+    ///
+    /// Panics if `R` is already written.
+    pub fn add_resource_read<R: 'static>(&mut self) {
+        add_read(&mut self.resource_reads, &self.resource_writes, TypeId::of::<R>(), std::any::type_name::<R>());
+    }
+
+    /// Declare a write of the resource `R`.
+    ///
+    /// Panics if `R` is already read or written.
+    pub fn add_resource_write<R: 'static>(&mut self) {
+        add_write(&self.resource_reads, &mut self.resource_writes, TypeId::of::<R>(), std::any::type_name::<R>());
+    }
+
+    /// Declare that [`Commands`] are queued.
+    pub fn add_commands(&mut self) {
+        self.commands = true;
+    }
+
+    /// Declare everything `other` reads and writes.
+    ///
+    /// Reading something twice is allowed, anything else conflicting panics.
+    pub fn extend(&mut self, other: &Access) {
+        for (id, name) in &other.reads {
+            add_read(&mut self.reads, &self.writes, *id, name);
+        }
+        for (id, name) in &other.writes {
+            add_write(&self.reads, &mut self.writes, *id, name);
+        }
+        for (id, name) in &other.resource_reads {
+            add_read(&mut self.resource_reads, &self.resource_writes, *id, name);
+        }
+        for (id, name) in &other.resource_writes {
+            add_write(&self.resource_reads, &mut self.resource_writes, *id, name);
+        }
+        self.commands |= other.commands;
+    }
+
+    /// Whether both can borrow what they declared at the same time.
+    pub fn is_compatible(&self, other: &Access) -> bool {
+        let disjoint = |writes: &HashMap<TypeId, &'static str>, reads: &HashMap<TypeId, &'static str>, other_writes: &HashMap<TypeId, &'static str>| writes.keys().all(|id| !reads.contains_key(id) && !other_writes.contains_key(id));
+        !(self.commands && other.commands) && disjoint(&self.writes, &other.reads, &other.writes) && disjoint(&other.writes, &self.reads, &self.writes) && disjoint(&self.resource_writes, &other.resource_reads, &other.resource_writes) && disjoint(&other.resource_writes, &self.resource_reads, &self.resource_writes)
+    }
+}
+
+fn add_read(reads: &mut HashMap<TypeId, &'static str>, writes: &HashMap<TypeId, &'static str>, id: TypeId, name: &'static str) {
+    if writes.contains_key(&id) {
+        panic!("Conflicting access to {name}: it is already borrowed mutably");
+    }
+    reads.insert(id, name);
+}
+
+fn add_write(reads: &HashMap<TypeId, &'static str>, writes: &mut HashMap<TypeId, &'static str>, id: TypeId, name: &'static str) {
+    if reads.contains_key(&id) || writes.contains_key(&id) {
+        panic!("Conflicting access to {name}: it is already borrowed");
+    }
+    writes.insert(id, name);
+}
+
+/// Types that can be fetched from a [`Query`].
+///
+/// # Safety
+///
+/// [`QueryData::access`] must declare every component borrowed by [`QueryData::init_fetch`],
+/// [`QueryData::required`] must contain every component that [`QueryData::fetch`]
+/// expects, and [`QueryData::fetch`] must only return mutable references to
+/// data belonging to the given entity.
+pub unsafe trait QueryData {
+    /// The item returned for each entity.
+    type Item<'q>;
+    /// The borrowed component stores.
+    type Fetch<'w>;
+
+    /// Declare the components this query borrows.
+    fn access(access: &mut Access);
+
+    /// Borrow the component stores from the world.
+    fn init_fetch(world: &World) -> Self::Fetch<'_>;
+
+    /// The components an entity must have to be fetched.
+    fn required(fetch: &Self::Fetch<'_>) -> BitField;
+
+    /// The entities of a required [`StorageKind::SparseSet`] store, if any.
+    ///
+    /// Iterating them instead of every entity skips entities that can not match.
+    fn dense_entities<'q>(_fetch: &'q Self::Fetch<'_>) -> Option<&'q [Entity]> {
+        None
+    }
+
+    /// Fetch the item of an entity.
+    ///
+    /// # Safety
+    ///
+    /// The entity must have every components in [`QueryData::required`], and
+    /// no other item of the same entity can be alive if this query writes.
+    unsafe fn fetch<'q>(fetch: &'q Self::Fetch<'_>, entity: Entity) -> Self::Item<'q>;
+}
+
+/// Marker for [`QueryData`] that does not write to any components.
+///
+/// # Safety
+///
+/// [`QueryData::access`] must not declare any writes.
+pub unsafe trait ReadOnlyQueryData: QueryData {}
+
+unsafe impl QueryData for Entity {
+    type Item<'q> = Entity;
+    type Fetch<'w> = ();
+
+    fn access(_access: &mut Access) {}
+
+    fn init_fetch(_world: &World) -> Self::Fetch<'_> {}
+
+    fn required(_fetch: &Self::Fetch<'_>) -> BitField {
+        0
+    }
+
+    unsafe fn fetch<'q>(_fetch: &'q Self::Fetch<'_>, entity: Entity) -> Self::Item<'q> {
+        entity
+    }
+}
+
+unsafe impl ReadOnlyQueryData for Entity {}
+
+/// The fetch of `&C`.
+pub struct ReadFetch<'w, C> {
+    components: RwLockReadGuard<'w, Components<C>>,
+    bit: BitField,
+}
+
+impl<C: Component> ReadFetch<'_, C> {
+    fn new(world: &World) -> ReadFetch<'_, C> {
+        ReadFetch { components: world.components::<C>(), bit: world.component_bit::<C>() }
+    }
+
+    fn dense_entities(&self) -> Option<&[Entity]> {
+        self.components.dense_entities()
+    }
+}
+
+unsafe impl<C: Component> QueryData for &C {
+    type Item<'q> = &'q C;
+    type Fetch<'w> = ReadFetch<'w, C>;
+
+    fn access(access: &mut Access) {
+        access.add_read::<C>();
+    }
+
+    fn init_fetch(world: &World) -> Self::Fetch<'_> {
+        ReadFetch::new(world)
+    }
+
+    fn required(fetch: &Self::Fetch<'_>) -> BitField {
+        fetch.bit
+    }
+
+    fn dense_entities<'q>(fetch: &'q Self::Fetch<'_>) -> Option<&'q [Entity]> {
+        fetch.dense_entities()
+    }
+
+    unsafe fn fetch<'q>(fetch: &'q Self::Fetch<'_>, entity: Entity) -> Self::Item<'q> {
+        &fetch.components.get_at(entity.index).expect("component is checked by the achetype").value
+    }
+}
+
+unsafe impl<C: Component> ReadOnlyQueryData for &C {}
+
+/// The fetch of `&mut C`.
+pub struct WriteFetch<'w, C> {
+    _guard: RwLockWriteGuard<'w, Components<C>>,
+    /// Pointer to the guarded [`Components`], used for handing out
+    /// mutable references of different entities at the same time.
+    components: *mut Components<C>,
+    /// The tick mutably fetched components are marked as changed with.
+    change_tick: Tick,
+    bit: BitField,
+}
+
+impl<C: Component> WriteFetch<'_, C> {
+    fn new(world: &World) -> WriteFetch<'_, C> {
+        let mut guard = world.components_mut::<C>();
+        let change_tick = guard.change_tick;
+        let components = &mut *guard as *mut Components<C>;
+        WriteFetch { _guard: guard, components, change_tick, bit: world.component_bit::<C>() }
+    }
+
+    /// Get the component at `index` and mark it as changed.
+    ///
+    /// # Safety
+    ///
+    /// No other reference to the component can be alive.
+    unsafe fn get_mut<'q>(&self, index: usize) -> Option<&'q mut C> {
+        // SAFETY: The write lock is held and `Components::get_ptr` does not
+        // create references to other components.
+        let slot = unsafe { &mut *(*self.components).get_ptr(index)? };
+        slot.ticks.changed = self.change_tick;
+        Some(&mut slot.value)
+    }
+
+    fn dense_entities(&self) -> Option<&[Entity]> {
+        // SAFETY: The write lock is held and the entities are never handed out mutably.
+        unsafe { (*self.components).dense_entities() }
+    }
+}
+
+unsafe impl<C: Component> QueryData for &mut C {
+    type Item<'q> = &'q mut C;
+    type Fetch<'w> = WriteFetch<'w, C>;
+
+    fn access(access: &mut Access) {
+        access.add_write::<C>();
+    }
+
+    fn init_fetch(world: &World) -> Self::Fetch<'_> {
+        WriteFetch::new(world)
+    }
+
+    fn required(fetch: &Self::Fetch<'_>) -> BitField {
+        fetch.bit
+    }
+
+    fn dense_entities<'q>(fetch: &'q Self::Fetch<'_>) -> Option<&'q [Entity]> {
+        fetch.dense_entities()
+    }
+
+    unsafe fn fetch<'q>(fetch: &'q Self::Fetch<'_>, entity: Entity) -> Self::Item<'q> {
+        // SAFETY: The caller guarantees that no other item of this entity is alive.
+        unsafe { fetch.get_mut(entity.index) }.expect("component is checked by the achetype")
+    }
+}
+
+unsafe impl<C: Component> QueryData for Option<&C> {
+    type Item<'q> = Option<&'q C>;
+    type Fetch<'w> = ReadFetch<'w, C>;
+
+    fn access(access: &mut Access) {
+        access.add_read::<C>();
+    }
+
+    fn init_fetch(world: &World) -> Self::Fetch<'_> {
+        ReadFetch::new(world)
+    }
+
+    fn required(_fetch: &Self::Fetch<'_>) -> BitField {
+        0
+    }
+
+    unsafe fn fetch<'q>(fetch: &'q Self::Fetch<'_>, entity: Entity) -> Self::Item<'q> {
+        fetch.components.get_at(entity.index).map(|slot| &slot.value)
+    }
+}
+
+unsafe impl<C: Component> ReadOnlyQueryData for Option<&C> {}
+
+unsafe impl<C: Component> QueryData for Option<&mut C> {
+    type Item<'q> = Option<&'q mut C>;
+    type Fetch<'w> = WriteFetch<'w, C>;
+
+    fn access(access: &mut Access) {
+        access.add_write::<C>();
+    }
+
+    fn init_fetch(world: &World) -> Self::Fetch<'_> {
+        WriteFetch::new(world)
+    }
+
+    fn required(_fetch: &Self::Fetch<'_>) -> BitField {
+        0
+    }
+
+    unsafe fn fetch<'q>(fetch: &'q Self::Fetch<'_>, entity: Entity) -> Self::Item<'q> {
+        // SAFETY: The caller guarantees that no other item of this entity is alive.
+        unsafe { fetch.get_mut(entity.index) }
+    }
+}
+
+/// Filters for a [`Query`] based on the components an entity has.
+///
+/// Filters only look at the entity's achetype so they do not borrow any
+/// component stores.
+pub trait QueryFilter {
+    /// Get the components an entity must have and must not have.
+    fn achetype(world: &World) -> QueryAchetype;
+}
+
+/// The components required and excluded by a [`Query`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueryAchetype {
+    /// The components an entity must have.
+    pub include: BitField,
+    /// The components an entity must not have.
+    pub exclude: BitField,
+}
+
+/// Filter entities that have the component `T`.
+pub struct With<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for With<T> {
+    fn achetype(world: &World) -> QueryAchetype {
+        QueryAchetype { include: world.component_bit::<T>(), exclude: 0 }
+    }
+}
+
+/// Filter entities that do not have the component `T`.
+pub struct Without<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for Without<T> {
+    fn achetype(world: &World) -> QueryAchetype {
+        QueryAchetype { include: 0, exclude: world.component_bit::<T>() }
+    }
+}
+
+macro_rules! query_tuple_impl {
+    ($($($t:ident)*,)*) => {
+        $(
+            #[allow(non_snake_case)]
+            unsafe impl<$($t: QueryData,)*> QueryData for ($($t,)*) {
+                type Item<'q> = ($($t::Item<'q>,)*);
+                type Fetch<'w> = ($($t::Fetch<'w>,)*);
+
+                fn access(access: &mut Access) {
+                    $($t::access(access);)*
+                }
+
+                fn init_fetch(world: &World) -> Self::Fetch<'_> {
+                    ($($t::init_fetch(world),)*)
+                }
+
+                fn required(fetch: &Self::Fetch<'_>) -> BitField {
+                    let ($($t,)*) = fetch;
+                    $($t::required($t))|*
+                }
+
+                fn dense_entities<'q>(fetch: &'q Self::Fetch<'_>) -> Option<&'q [Entity]> {
+                    let ($($t,)*) = fetch;
+                    // Iterate the shortest store.
+                    [$($t::dense_entities($t),)*].into_iter().flatten().min_by_key(|entities| entities.len())
+                }
+
+                unsafe fn fetch<'q>(fetch: &'q Self::Fetch<'_>, entity: Entity) -> Self::Item<'q> {
+                    let ($($t,)*) = fetch;
+                    unsafe { ($($t::fetch($t, entity),)*) }
+                }
+            }
+
+            unsafe impl<$($t: ReadOnlyQueryData,)*> ReadOnlyQueryData for ($($t,)*) {}
+
+            impl<$($t: QueryFilter,)*> QueryFilter for ($($t,)*) {
+                fn achetype(world: &World) -> QueryAchetype {
+                    let mut achetype = QueryAchetype::default();
+                    $(
+                        let next = $t::achetype(world);
+                        achetype.include |= next.include;
+                        achetype.exclude |= next.exclude;
+                    )*
+                    achetype
+                }
+            }
+        )*
+    };
+}
+
+query_tuple_impl! {
+    A,
+    A B,
+    A B C,
+    A B C D,
+    A B C D E,
+    A B C D E F,
+}
+
+impl QueryFilter for () {
+    fn achetype(_world: &World) -> QueryAchetype {
+        QueryAchetype::default()
+    }
+}
+
+/// Borrowed component stores for iterating over every entity that matches
+/// `Q` and the filter `F`.
+///
+/// ```ignore
+/// let mut query = world.query::<(&Vec2i32, &mut Sprite, Option<&DamagedTimer>)>();
+/// for (position, sprite, damaged_timer) in &mut query {
+///     // ...
+/// }
+/// ```
+pub struct Query<'w, Q: QueryData, F: QueryFilter = ()> {
+    entities: &'w Entities,
+    fetch: Q::Fetch<'w>,
+    achetype: QueryAchetype,
+    _filter: PhantomData<F>,
+}
+
+impl<'w, Q: QueryData, F: QueryFilter> Query<'w, Q, F> {
+    /// Borrow the component stores used by the query.
+    ///
+    /// Panics if the query borrows the same component mutably more than once
+    /// or if any of the stores is already borrowed elsewhere.
+    pub fn new(world: &'w World) -> Query<'w, Q, F> {
+        let mut access = Access::default();
+        Q::access(&mut access);
+        let fetch = Q::init_fetch(world);
+        let mut achetype = F::achetype(world);
+        achetype.include |= Q::required(&fetch);
+        Query { entities: world.entities(), fetch, achetype, _filter: PhantomData }
+    }
+
+    fn matches(&self, index: usize) -> bool {
+        let components = self.entities.achetype[index].components;
+        components & self.achetype.include == self.achetype.include && components & self.achetype.exclude == 0
+    }
+
+    fn missing(entity: Entity) -> EcsError {
+        EcsError::MissingComponent { entity, type_name: std::any::type_name::<Q>() }
+    }
+
+    /// Iterate over every matching entity.
+    pub fn iter(&self) -> QueryIter<'_, 'w, Q, F>
+    where
+        Q: ReadOnlyQueryData,
+    {
+        QueryIter { dense: Q::dense_entities(&self.fetch), query: self, index: 0 }
+    }
+
+    /// Iterate over every matching entity mutably.
+    pub fn iter_mut(&mut self) -> QueryIter<'_, 'w, Q, F> {
+        QueryIter { dense: Q::dense_entities(&self.fetch), query: self, index: 0 }
+    }
+
+    /// Get the item of the only matching entity.
+    ///
+    /// Returns [`None`] if no entity or more than one entity matches.
+    pub fn single(&self) -> Option<Q::Item<'_>>
+    where
+        Q: ReadOnlyQueryData,
+    {
+        let mut iter = self.iter();
+        let item = iter.next()?;
+        iter.next().is_none().then_some(item)
+    }
+
+    /// Get the item of the only matching entity mutably.
+    ///
+    /// Returns [`None`] if no entity or more than one entity matches.
+    pub fn single_mut(&mut self) -> Option<Q::Item<'_>> {
+        let mut iter = self.iter_mut();
+        let item = iter.next()?;
+        iter.next().is_none().then_some(item)
+    }
+
+    /// Get the item of a single entity.
+    pub fn get(&self, entity: Entity) -> Result<Q::Item<'_>, EcsError>
+    where
+        Q: ReadOnlyQueryData,
+    {
+        self.entities.check(entity)?;
+        if !self.matches(entity.index) {
+            return Err(Self::missing(entity));
+        }
+        // SAFETY: The entity matches and the query is read-only.
+        Ok(unsafe { Q::fetch(&self.fetch, entity) })
+    }
+
+    /// Get the item of a single entity mutably.
+    pub fn get_mut(&mut self, entity: Entity) -> Result<Q::Item<'_>, EcsError> {
+        self.entities.check(entity)?;
+        if !self.matches(entity.index) {
+            return Err(Self::missing(entity));
+        }
+        // SAFETY: The entity matches and `self` is borrowed mutably so no
+        // other item can be alive.
+        Ok(unsafe { Q::fetch(&self.fetch, entity) })
+    }
+}
+
+/// Iterator over the items of a [`Query`].
+pub struct QueryIter<'q, 'w, Q: QueryData, F: QueryFilter> {
+    query: &'q Query<'w, Q, F>,
+    /// The entities to visit instead of every entity, see [`QueryData::dense_entities`].
+    dense: Option<&'q [Entity]>,
+    index: usize,
+}
+
+impl<'q, Q: QueryData, F: QueryFilter> Iterator for QueryIter<'q, '_, Q, F> {
+    type Item = Q::Item<'q>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entities = self.query.entities;
+        if let Some(dense) = self.dense {
+            while let Some(&entity) = dense.get(self.index) {
+                self.index += 1;
+                if !entities.exists(entity) || !self.query.matches(entity.index) {
+                    continue;
+                }
+                // SAFETY: Same as below, an entity is stored once in a sparse set.
+                return Some(unsafe { Q::fetch(&self.query.fetch, entity) });
+            }
+            return None;
+        }
+        while self.index < entities.generations.len() {
+            let index = self.index;
+            self.index += 1;
+            if !entities.achetype[index].valid || !self.query.matches(index) {
+                continue;
+            }
+            let entity = Entity { index, generation: entities.generations[index] };
+            // SAFETY: The entity matches and each entity is only visited once.
+            // The iterator is created from either a read-only query or a
+            // mutably borrowed query.
+            return Some(unsafe { Q::fetch(&self.query.fetch, entity) });
+        }
+        None
+    }
+}
+
+impl<'q, 'w, Q: ReadOnlyQueryData, F: QueryFilter> IntoIterator for &'q Query<'w, Q, F> {
+    type Item = Q::Item<'q>;
+    type IntoIter = QueryIter<'q, 'w, Q, F>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'q, 'w, Q: QueryData, F: QueryFilter> IntoIterator for &'q mut Query<'w, Q, F> {
+    type Item = Q::Item<'q>;
+    type IntoIter = QueryIter<'q, 'w, Q, F>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<Q: QueryData + 'static, F: QueryFilter + 'static> SystemParam for Query<'_, Q, F> {
+    type State = ();
+    type Item<'w, 's> = Query<'w, Q, F>;
+
+    fn access(access: &mut Access) {
+        Q::access(access);
+    }
+
+    fn fetch<'w>(world: &'w World, _state: &mut ()) -> Query<'w, Q, F> {
+        Query::new(world)
+    }
+}
+
+impl World {
+    /// Borrow the components needed for iterating over `Q`.
+    pub fn query<Q: QueryData>(&self) -> Query<'_, Q> {
+        Query::new(self)
+    }
+
+    /// Borrow the components needed for iterating over `Q` filtered by `F`.
+    pub fn query_filtered<Q: QueryData, F: QueryFilter>(&self) -> Query<'_, Q, F> {
+        Query::new(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    impl Component for Position {}
+
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32);
+    impl Component for Velocity {}
+
+    #[derive(Debug, PartialEq)]
+    struct Trail(i32);
+    impl Component for Trail {
+        const STORAGE: StorageKind = StorageKind::SparseSet;
+    }
+
+    struct Frozen;
+    impl Component for Frozen {}
+
+    #[test]
+    fn iterate_multiple_components() {
+        let mut world = World::default();
+        let moving = world.spawn();
+        world.insert(moving, Position(0)).unwrap();
+        world.insert(moving, Velocity(2)).unwrap();
+        let still = world.spawn();
+        world.insert(still, Position(5)).unwrap();
+        let frozen = world.spawn();
+        world.insert(frozen, Position(1)).unwrap();
+        world.insert(frozen, Velocity(1)).unwrap();
+        world.insert(frozen, Frozen).unwrap();
+
+        let mut query = world.query_filtered::<(&mut Position, &Velocity), (With<Velocity>, Without<Frozen>)>();
+        for (position, velocity) in &mut query {
+            position.0 += velocity.0;
+        }
+        drop(query);
+
+        let query = world.query::<(Entity, &Position, Option<&Velocity>)>();
+        let items: Vec<_> = query.iter().collect();
+        assert_eq!(items, vec![(moving, &Position(2), Some(&Velocity(2))), (still, &Position(5), None), (frozen, &Position(1), Some(&Velocity(1)))]);
+        assert_eq!(world.query_filtered::<Entity, With<Frozen>>().iter().collect::<Vec<_>>(), vec![frozen]);
+    }
+
+    #[test]
+    fn get_single_entity() {
+        let mut world = World::default();
+        let entity = world.spawn();
+        world.insert(entity, Position(3)).unwrap();
+
+        let mut query = world.query::<&mut Position>();
+        query.get_mut(entity).unwrap().0 = 4;
+        assert_eq!(query.get_mut(entity).unwrap(), &mut Position(4));
+        drop(query);
+
+        let type_name = std::any::type_name::<(&Position, &Velocity)>();
+        assert_eq!(world.query::<(&Position, &Velocity)>().get(entity).unwrap_err(), EcsError::MissingComponent { entity, type_name });
+    }
+
+    #[test]
+    fn single_entity_with_marker() {
+        let mut world = World::default();
+        let first = world.spawn();
+        world.insert(first, Position(1)).unwrap();
+        world.insert(first, Frozen).unwrap();
+        assert_eq!(world.query_filtered::<&Position, With<Frozen>>().single(), Some(&Position(1)));
+
+        let second = world.spawn();
+        world.insert(second, Frozen).unwrap();
+        assert_eq!(world.query_filtered::<Entity, With<Frozen>>().single(), None);
+        world.remove::<Frozen>(first).unwrap();
+        assert_eq!(world.query_filtered::<Entity, With<Frozen>>().single(), Some(second));
+        world.query_filtered::<&mut Position, Without<Frozen>>().single_mut().unwrap().0 = 2;
+        assert_eq!(world.query::<&Position>().single(), Some(&Position(2)));
+    }
+
+    #[test]
+    fn despawned_entities_are_skipped() {
+        let mut world = World::default();
+        let entity = world.spawn();
+        world.insert(entity, Position(3)).unwrap();
+        world.despawn(entity).unwrap();
+        assert_eq!(world.query::<&Position>().iter().count(), 0);
+    }
+
+    #[test]
+    fn query_mixed_storages() {
+        let mut world = World::default();
+        let moving = world.spawn();
+        let frozen = world.spawn();
+        for entity in [moving, frozen] {
+            world.insert(entity, Position(0)).unwrap();
+            world.insert(entity, Trail(0)).unwrap();
+        }
+        world.insert(moving, Velocity(2)).unwrap();
+
+        for (position, trail, velocity) in &mut world.query::<(&mut Position, &mut Trail, &Velocity)>() {
+            position.0 += velocity.0;
+            trail.0 += 1;
+        }
+
+        let query = world.query::<(Entity, &Position, &Trail)>();
+        assert_eq!(query.iter().collect::<Vec<_>>(), vec![(moving, &Position(2), &Trail(1)), (frozen, &Position(0), &Trail(0))]);
+    }
+
+    #[test]
+    fn mutable_fetch_marks_changed() {
+        let mut world = World::default();
+        let read = world.spawn();
+        let written = world.spawn();
+        world.insert(read, Position(0)).unwrap();
+        world.insert(written, Position(0)).unwrap();
+        world.insert(written, Velocity(1)).unwrap();
+        world.maintain();
+
+        let _ = world.query::<&Position>().iter().count();
+        for (position, velocity) in &mut world.query::<(&mut Position, &Velocity)>() {
+            position.0 += velocity.0;
+        }
+
+        let positions = world.components::<Position>();
+        assert_eq!(positions.changed_since(world.entities(), world.change_tick()).collect::<Vec<_>>(), vec![(written, &Position(1))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Conflicting access")]
+    fn conflicting_query_panics() {
+        let world = World::default();
+        let _query = world.query::<(&mut Position, &Position)>();
+    }
+}
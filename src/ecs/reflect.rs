@@ -0,0 +1,170 @@
+//! Listing and editing the fields of components at runtime.
+
+use super::*;
+use std::str::FromStr;
+
+/// A type whose fields can be listed and edited by name.
+///
+/// ```ignore
+/// impl Reflect for MaxHealth {
+///     fn fields(&self) -> Vec<(&'static str, String)> {
+///         vec![("max", self.0.to_string())]
+///     }
+///
+///     fn set_field(&mut self, name: &str, value: &str) -> Result<(), ReflectError> {
+///         match name {
+///             "max" => self.0 = parse_field(name, value)?,
+///             _ => return Err(ReflectError::UnknownField(name.to_owned())),
+///         }
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait Reflect {
+    /// The name and formatted value of every field.
+    fn fields(&self) -> Vec<(&'static str, String)>;
+
+    /// Parse `value` and set it to the field called `name`.
+    fn set_field(&mut self, name: &str, value: &str) -> Result<(), ReflectError>;
+}
+
+/// Parse `value` for the field called `field` with [`FromStr`].
+pub fn parse_field<T: FromStr>(field: &str, value: &str) -> Result<T, ReflectError> {
+    value.trim().parse().map_err(|_| ReflectError::InvalidValue { field: field.to_owned(), value: value.to_owned() })
+}
+
+/// Errors returned when editing a component through [`Reflect`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReflectError {
+    /// No component was registered with this name in the [`ReflectRegistry`].
+    UnknownComponent(String),
+    /// The component has no field with this name.
+    UnknownField(String),
+    /// The value could not be parsed for the field.
+    InvalidValue {
+        /// The name of the field.
+        field: String,
+        /// The value that was given.
+        value: String,
+    },
+    /// The entity does not exist or does not have the component.
+    Ecs(EcsError),
+}
+
+impl std::error::Error for ReflectError {}
+
+impl std::fmt::Display for ReflectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReflectError::UnknownComponent(name) => write!(f, "Component {name} is not registered"),
+            ReflectError::UnknownField(name) => write!(f, "Field {name} does not exist"),
+            ReflectError::InvalidValue { field, value } => write!(f, "Invalid value {value:?} for field {field}"),
+            ReflectError::Ecs(error) => error.fmt(f),
+        }
+    }
+}
+
+impl From<EcsError> for ReflectError {
+    fn from(error: EcsError) -> Self {
+        ReflectError::Ecs(error)
+    }
+}
+
+/// The name and formatted value of every field, see [`Reflect::fields`].
+pub type Fields = Vec<(&'static str, String)>;
+
+/// A component registered in the [`ReflectRegistry`].
+struct Registration {
+    name: &'static str,
+    fields: fn(&World, Entity) -> Option<Fields>,
+    set_field: fn(&World, Entity, &str, &str) -> Result<(), ReflectError>,
+}
+
+/// A global resource for the [`Reflect`] components that can be found by name.
+///
+/// Components are opt-in, only the registered ones are listed.
+#[derive(Default)]
+pub struct ReflectRegistry {
+    registrations: Vec<Registration>,
+}
+
+impl ReflectRegistry {
+    /// Register the component `T` under `name`.
+    pub fn register<T: Component + Reflect + Clone>(&mut self, name: &'static str) -> &mut Self {
+        self.registrations.push(Registration {
+            name,
+            fields: |world, entity| world.components::<T>().get(world.entities(), entity).ok().map(T::fields),
+            set_field: |world, entity, field, value| {
+                let mut component = world.components::<T>().get(world.entities(), entity)?.clone();
+                component.set_field(field, value)?;
+                world.commands().insert(entity, component);
+                Ok(())
+            },
+        });
+        self
+    }
+
+    /// The name and fields of every registered component the entity has, in the order they were registered.
+    ///
+    /// Borrows the component stores, so none of them must be mutably borrowed.
+    pub fn reflect(&self, world: &World, entity: Entity) -> Vec<(&'static str, Fields)> {
+        self.registrations.iter().filter_map(|registration| Some((registration.name, (registration.fields)(world, entity)?))).collect()
+    }
+
+    /// Set the field of the entity's component registered as `component`.
+    ///
+    /// The edited component is inserted with [`Commands`] so its [`ComponentHooks`] run.
+    pub fn set_field(&self, world: &World, entity: Entity, component: &str, field: &str, value: &str) -> Result<(), ReflectError> {
+        let registration = self.registrations.iter().find(|registration| registration.name == component).ok_or_else(|| ReflectError::UnknownComponent(component.to_owned()))?;
+        (registration.set_field)(world, entity, field, value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Position(i32, i32);
+    impl Component for Position {}
+
+    impl Reflect for Position {
+        fn fields(&self) -> Vec<(&'static str, String)> {
+            vec![("x", self.0.to_string()), ("y", self.1.to_string())]
+        }
+
+        fn set_field(&mut self, name: &str, value: &str) -> Result<(), ReflectError> {
+            match name {
+                "x" => self.0 = parse_field(name, value)?,
+                "y" => self.1 = parse_field(name, value)?,
+                _ => return Err(ReflectError::UnknownField(name.to_owned())),
+            }
+            Ok(())
+        }
+    }
+
+    struct Hidden;
+    impl Component for Hidden {}
+
+    #[test]
+    fn registered_components_are_listed_and_edited() {
+        let mut world = World::default();
+        let entity = world.spawn();
+        world.insert(entity, Position(1, 2)).unwrap();
+        world.insert(entity, Hidden).unwrap();
+        let mut registry = ReflectRegistry::default();
+        registry.register::<Position>("Position");
+
+        assert_eq!(registry.reflect(&world, entity), vec![("Position", vec![("x", "1".to_owned()), ("y", "2".to_owned())])]);
+        registry.set_field(&world, entity, "Position", "y", " -3").unwrap();
+        world.apply_commands();
+        assert_eq!(world.query::<&Position>().get(entity), Ok(&Position(1, -3)));
+
+        assert_eq!(registry.set_field(&world, entity, "Position", "z", "0"), Err(ReflectError::UnknownField("z".to_owned())));
+        assert_eq!(registry.set_field(&world, entity, "Position", "x", "one"), Err(ReflectError::InvalidValue { field: "x".to_owned(), value: "one".to_owned() }));
+        assert_eq!(registry.set_field(&world, entity, "Hidden", "x", "0"), Err(ReflectError::UnknownComponent("Hidden".to_owned())));
+        let other = world.spawn();
+        assert!(registry.reflect(&world, other).is_empty());
+        assert!(matches!(registry.set_field(&world, other, "Position", "x", "0"), Err(ReflectError::Ecs(EcsError::MissingComponent { .. }))));
+    }
+}
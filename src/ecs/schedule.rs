@@ -0,0 +1,489 @@
+//! Running systems in order.
+
+use super::*;
+
+/// The stages of a frame, ran in this order.
+///
+/// Queued [`Commands`] are applied at the end of every stage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Stage {
+    /// Reading the terminal input.
+    Input,
+    /// The game logic.
+    Update,
+    /// Reacting to what happened during [`Stage::Update`].
+    PostUpdate,
+    /// Drawing to the terminal.
+    Render,
+}
+
+impl Stage {
+    /// Every stage in the order they run.
+    pub const ALL: [Stage; 4] = [Stage::Input, Stage::Update, Stage::PostUpdate, Stage::Render];
+
+    /// The stages of one simulation step, every stage but [`Stage::Render`].
+    pub const SIMULATION: [Stage; 3] = [Stage::Input, Stage::Update, Stage::PostUpdate];
+}
+
+/// A system ran with `&World` every frame, only if its run conditions hold.
+pub type SystemFn = Box<dyn FnMut(&World) + Send>;
+
+/// A condition deciding whether a system runs this frame.
+pub type RunCondition = Box<dyn FnMut(&World) -> bool + Send>;
+
+/// A system registered in a [`Schedule`] and how it should be ran.
+pub struct System {
+    name: &'static str,
+    stage: Stage,
+    run: SystemFn,
+    conditions: Vec<RunCondition>,
+    before: Vec<&'static str>,
+    after: Vec<&'static str>,
+    /// What the system borrows, a system that takes `&World` and declared nothing always runs alone.
+    access: Option<Access>,
+    /// What the run conditions borrow, [`None`] if any of them takes `&World`.
+    condition_access: Option<Access>,
+}
+
+impl System {
+    /// The unique name of the system.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The stage the system runs in.
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    /// The components and resources the system declared it borrows.
+    ///
+    /// Declared automatically for systems taking [`SystemParam`]s.
+    /// [`None`] if the system takes `&World` and nothing was declared, such
+    /// system never runs at the same time as others.
+    pub fn access(&self) -> Option<&Access> {
+        self.access.as_ref()
+    }
+
+    /// Run this system before the system named `name`.
+    pub fn before(&mut self, name: &'static str) -> &mut Self {
+        self.before.push(name);
+        self
+    }
+
+    /// Run this system after the system named `name`.
+    pub fn after(&mut self, name: &'static str) -> &mut Self {
+        self.after.push(name);
+        self
+    }
+
+    /// Only run this system when `condition` returns `true`.
+    ///
+    /// Conditions are checked right before the batch of the system runs. The
+    /// system never shares a batch with earlier systems writing what its
+    /// conditions read, and a condition taking `&World` always starts a new batch.
+    pub fn run_if<Params>(&mut self, condition: impl IntoRunCondition<Params>) -> &mut Self {
+        let (condition, access) = condition.into_run_condition();
+        self.conditions.push(condition);
+        self.condition_access = match (self.condition_access.take(), access) {
+            (Some(mut condition_access), Some(access)) => {
+                condition_access.extend(&access);
+                Some(condition_access)
+            }
+            _ => None,
+        };
+        self
+    }
+
+    /// Declare that the system reads the component `T`.
+    pub fn reads<T: Component>(&mut self) -> &mut Self {
+        self.access.get_or_insert_default().add_read::<T>();
+        self
+    }
+
+    /// Declare that the system writes the component `T`.
+    pub fn writes<T: Component>(&mut self) -> &mut Self {
+        self.access.get_or_insert_default().add_write::<T>();
+        self
+    }
+
+    /// Declare that the system reads the resource `R`.
+    pub fn reads_resource<R: Send + Sync + 'static>(&mut self) -> &mut Self {
+        self.access.get_or_insert_default().add_resource_read::<R>();
+        self
+    }
+
+    /// Declare that the system writes the resource `R`.
+    pub fn writes_resource<R: Send + Sync + 'static>(&mut self) -> &mut Self {
+        self.access.get_or_insert_default().add_resource_write::<R>();
+        self
+    }
+
+    /// Declare that the system queues [`Commands`].
+    pub fn uses_commands(&mut self) -> &mut Self {
+        self.access.get_or_insert_default().add_commands();
+        self
+    }
+
+    /// Whether all of its conditions hold.
+    fn should_run(&mut self, world: &World) -> bool {
+        self.conditions.iter_mut().all(|condition| condition(world))
+    }
+}
+
+/// How a [`Schedule`] runs its systems.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Executor {
+    /// Systems that can borrow what they declared at the same time, and are
+    /// not ordered against each other, run together on scoped threads.
+    #[default]
+    Parallel,
+    /// Every system runs one after another on the calling thread.
+    SingleThreaded,
+}
+
+/// Systems grouped by [`Stage`] and sorted by their `before`/`after` ordering.
+///
+/// ```ignore
+/// let mut schedule = Schedule::default();
+/// schedule.add_system(Stage::Update, "enemy_follow", enemy_follow_system).after("player_movement").run_if(playing);
+/// loop {
+///     schedule.run(&mut world);
+/// }
+/// ```
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<System>,
+    executor: Executor,
+    /// Index of the systems of each stage, grouped in batches that run at the same time.
+    ///
+    /// Built again whenever a system is added or the executor changes.
+    plan: Option<Vec<Vec<Vec<usize>>>>,
+}
+
+impl Schedule {
+    /// Register a system.
+    ///
+    /// Systems in the same stage without any ordering run in the order they were added.
+    /// Panics if a system with the same name already exists.
+    pub fn add_system<Params>(&mut self, stage: Stage, name: &'static str, system: impl IntoSystem<Params>) -> &mut System {
+        if self.systems.iter().any(|s| s.name == name) {
+            panic!("System {name} is already in the schedule");
+        }
+        self.plan = None;
+        let (run, access) = system.into_system();
+        self.systems.push(System { name, stage, run, conditions: Vec::new(), before: Vec::new(), after: Vec::new(), access, condition_access: Some(Access::default()) });
+        self.systems.last_mut().expect("system is just pushed")
+    }
+
+    /// Set how the systems are ran.
+    pub fn set_executor(&mut self, executor: Executor) {
+        self.executor = executor;
+        self.plan = None;
+    }
+
+    /// Run every stage, applying commands after each of them.
+    ///
+    /// The run conditions of a batch are all checked before the batch runs.
+    pub fn run(&mut self, world: &mut World) {
+        self.run_stages(world, &Stage::ALL);
+    }
+
+    /// Run only the given stages, in the order of [`Stage::ALL`], applying commands after each of them.
+    ///
+    /// Used to run the simulation and the rendering at different rates.
+    pub fn run_stages(&mut self, world: &mut World, stages: &[Stage]) {
+        let plan = self.plan.take().unwrap_or_else(|| self.plan());
+        for (_, stage) in Stage::ALL.iter().zip(&plan).filter(|(stage, _)| stages.contains(stage)) {
+            for batch in stage {
+                self.run_batch(batch, world);
+            }
+            world.apply_commands();
+        }
+        self.plan = Some(plan);
+    }
+
+    fn run_batch(&mut self, batch: &[usize], world: &World) {
+        let mut systems = self.systems.iter_mut().enumerate().filter(|(i, _)| batch.contains(i)).map(|(_, system)| system).collect::<Vec<_>>();
+        systems.retain_mut(|system| system.should_run(world));
+        match systems.as_mut_slice() {
+            [] => {}
+            [system] => (system.run)(world),
+            systems => std::thread::scope(|scope| {
+                for system in systems {
+                    scope.spawn(|| (system.run)(world));
+                }
+            }),
+        }
+    }
+
+    /// Group the sorted systems of each stage into batches.
+    ///
+    /// A system joins the current batch if it can run at the same time as
+    /// every system in it, and its run conditions read nothing they write,
+    /// otherwise it starts the next batch. Batches keep the sorted order so
+    /// the result is the same as running one by one.
+    fn plan(&self) -> Vec<Vec<Vec<usize>>> {
+        let (order, edges) = self.sort();
+        let parallel = |batch: &[usize], next: usize| {
+            let (Some(access), Some(condition_access)) = (&self.systems[next].access, &self.systems[next].condition_access) else { return false };
+            batch.iter().all(|i| !edges[*i].contains(&next) && self.systems[*i].access.as_ref().is_some_and(|other| other.is_compatible(access) && other.is_compatible(condition_access)))
+        };
+        let mut plan = Vec::new();
+        for stage in Stage::ALL {
+            let mut batches: Vec<Vec<usize>> = Vec::new();
+            for next in order.iter().copied().filter(|i| self.systems[*i].stage == stage) {
+                match batches.last_mut() {
+                    Some(batch) if self.executor == Executor::Parallel && parallel(batch, next) => batch.push(next),
+                    _ => batches.push(vec![next]),
+                }
+            }
+            plan.push(batches);
+        }
+        plan
+    }
+
+    /// Sort the systems by stage, then by their ordering.
+    ///
+    /// Also returns the systems that must run after each system in the same stage.
+    /// Panics if an ordering names an unknown system, points to a system in
+    /// an earlier stage, or forms a cycle.
+    fn sort(&self) -> (Vec<usize>, Vec<Vec<usize>>) {
+        let index_of = |name: &str| self.systems.iter().position(|s| s.name == name);
+        // `edges[i]` are the systems that must run after the system `i`.
+        let mut edges = vec![Vec::new(); self.systems.len()];
+        for (i, system) in self.systems.iter().enumerate() {
+            let ordering = system.before.iter().map(|name| (*name, true)).chain(system.after.iter().map(|name| (*name, false)));
+            for (name, before) in ordering {
+                let Some(other) = index_of(name) else {
+                    panic!("System {} is ordered against unknown system {name}", system.name);
+                };
+                let (first, then) = if before { (i, other) } else { (other, i) };
+                match self.systems[first].stage.cmp(&self.systems[then].stage) {
+                    std::cmp::Ordering::Less => {}
+                    std::cmp::Ordering::Equal => edges[first].push(then),
+                    std::cmp::Ordering::Greater => panic!("System {} can not run after {} from a later stage", self.systems[then].name, self.systems[first].name),
+                }
+            }
+        }
+
+        let mut incoming = vec![0; self.systems.len()];
+        for then in edges.iter().flatten() {
+            incoming[*then] += 1;
+        }
+        let mut order = Vec::with_capacity(self.systems.len());
+        for stage in Stage::ALL {
+            let in_stage = (0..self.systems.len()).filter(|i| self.systems[*i].stage == stage).collect::<Vec<_>>();
+            // Always pick the earliest added system that is ready so the order is stable.
+            while let Some(next) = in_stage.iter().copied().find(|i| incoming[*i] == 0 && !order.contains(i)) {
+                order.push(next);
+                for then in &edges[next] {
+                    incoming[*then] -= 1;
+                }
+            }
+            if let Some(stuck) = in_stage.iter().find(|i| !order.contains(*i)) {
+                panic!("System {} is in an ordering cycle", self.systems[*stuck].name);
+            }
+        }
+        (order, edges)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Default)]
+    struct Log(Vec<&'static str>);
+
+    fn logger(name: &'static str) -> impl FnMut(&World) + Send {
+        move |world| world.resource_mut::<Log>().0.push(name)
+    }
+
+    #[test]
+    fn systems_run_by_stage_then_ordering() {
+        let mut world = World::default();
+        world.insert_resource(Log::default());
+
+        let mut schedule = Schedule::default();
+        schedule.add_system(Stage::Render, "render", logger("render"));
+        schedule.add_system(Stage::Update, "enemy", logger("enemy")).after("player");
+        schedule.add_system(Stage::Update, "player", logger("player"));
+        schedule.add_system(Stage::Update, "timer", logger("timer")).before("player");
+        schedule.add_system(Stage::Input, "input", logger("input")).before("render");
+        schedule.run(&mut world);
+
+        assert_eq!(world.resource::<Log>().0, vec!["input", "timer", "player", "enemy", "render"]);
+    }
+
+    #[test]
+    fn run_conditions_are_checked_every_time() {
+        let mut world = World::default();
+        world.insert_resource(Log::default());
+
+        let mut schedule = Schedule::default();
+        schedule.add_system(Stage::Update, "first", logger("first"));
+        schedule.add_system(Stage::Update, "once", logger("once")).run_if(|world: &World| world.resource::<Log>().0.len() < 2);
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+
+        assert_eq!(world.resource::<Log>().0, vec!["first", "once", "first"]);
+    }
+
+    #[derive(Default)]
+    struct Flag(bool);
+
+    fn flip_then_check(executor: Executor) -> Vec<&'static str> {
+        let mut world = World::default();
+        world.insert_resource(Log::default());
+        world.insert_resource(Flag::default());
+
+        let mut schedule = Schedule::default();
+        schedule.set_executor(executor);
+        schedule.add_system(Stage::Update, "flip", |mut flag: ResMut<Flag>| flag.0 = true);
+        schedule.add_system(Stage::Update, "flagged", logger("flagged")).writes_resource::<Log>().run_if(|flag: Res<Flag>| flag.0);
+        schedule.run(&mut world);
+        world.resource::<Log>().0.clone()
+    }
+
+    #[test]
+    fn run_conditions_see_earlier_systems_of_the_batch() {
+        assert_eq!(flip_then_check(Executor::SingleThreaded), vec!["flagged"]);
+        assert_eq!(flip_then_check(Executor::Parallel), vec!["flagged"]);
+    }
+
+    #[test]
+    fn only_the_given_stages_run() {
+        let mut world = World::default();
+        world.insert_resource(Log::default());
+
+        let mut schedule = Schedule::default();
+        schedule.add_system(Stage::Render, "render", logger("render"));
+        schedule.add_system(Stage::Update, "update", logger("update"));
+        schedule.add_system(Stage::Input, "input", logger("input"));
+        schedule.run_stages(&mut world, &Stage::SIMULATION);
+        schedule.run_stages(&mut world, &Stage::SIMULATION);
+        schedule.run_stages(&mut world, &[Stage::Render]);
+
+        assert_eq!(world.resource::<Log>().0, vec!["input", "update", "input", "update", "render"]);
+    }
+
+    #[test]
+    fn commands_are_applied_between_stages() {
+        let mut world = World::default();
+
+        let mut schedule = Schedule::default();
+        schedule.add_system(Stage::Update, "spawn", |world: &World| {
+            world.commands().spawn();
+        });
+        schedule.add_system(Stage::PostUpdate, "count", |world: &World| assert_eq!(world.entities().iter().count(), 1));
+        schedule.run(&mut world);
+    }
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    struct Position(i32);
+    impl Component for Position {}
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    struct Velocity(i32);
+    impl Component for Velocity {}
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    struct Age(u32);
+    impl Component for Age {}
+
+    fn simulate(executor: Executor) -> World {
+        let mut world = World::default();
+        world.insert_resource(Log::default());
+        for i in 0..20 {
+            let entity = world.spawn();
+            world.insert(entity, Position(i)).unwrap();
+            world.insert(entity, Velocity(i % 3 - 1)).unwrap();
+        }
+
+        let mut schedule = Schedule::default();
+        schedule.set_executor(executor);
+        schedule.add_system(Stage::Update, "accelerate", |mut query: Query<&mut Velocity>| query.iter_mut().for_each(|velocity| velocity.0 += 1));
+        schedule.add_system(Stage::Update, "age", |mut query: Query<&mut Age>| query.iter_mut().for_each(|age| age.0 += 1));
+        schedule.add_system(Stage::Update, "log", logger("log")).writes_resource::<Log>();
+        schedule.add_system(Stage::Update, "movement", |mut query: Query<(&mut Position, &Velocity)>| query.iter_mut().for_each(|(position, velocity)| position.0 += velocity.0)).after("accelerate");
+        schedule
+            .add_system(Stage::Update, "spawn", |mut commands: Commands, query: Query<(Entity, &Position)>| {
+                for (entity, position) in query.iter() {
+                    if position.0 % 7 == 0 {
+                        commands.spawn().insert(Age(0)).insert(*position);
+                        commands.despawn(entity);
+                    }
+                }
+            })
+            .after("movement");
+        for _ in 0..10 {
+            world.maintain();
+            schedule.run(&mut world);
+        }
+        world
+    }
+
+    #[test]
+    fn parallel_and_serial_runs_produce_identical_worlds() {
+        let serial = simulate(Executor::SingleThreaded);
+        let parallel = simulate(Executor::Parallel);
+
+        assert_eq!(serial.entities().iter().collect::<Vec<_>>(), parallel.entities().iter().collect::<Vec<_>>());
+        assert_eq!(serial.query::<(Entity, &Position)>().iter().collect::<Vec<_>>(), parallel.query::<(Entity, &Position)>().iter().collect::<Vec<_>>());
+        assert_eq!(serial.query::<(Entity, &Velocity)>().iter().collect::<Vec<_>>(), parallel.query::<(Entity, &Velocity)>().iter().collect::<Vec<_>>());
+        assert_eq!(serial.query::<(Entity, &Age)>().iter().collect::<Vec<_>>(), parallel.query::<(Entity, &Age)>().iter().collect::<Vec<_>>());
+        assert_eq!(serial.resource::<Log>().0, parallel.resource::<Log>().0);
+        assert!(serial.query::<&Age>().iter().count() > 0);
+    }
+
+    #[test]
+    fn compatible_systems_run_at_the_same_time() {
+        use std::sync::{Arc, atomic::AtomicBool};
+
+        // Each system waits for the other one to start, which only finishes if both run at once.
+        let wait_for = |started: Arc<AtomicBool>, other: Arc<AtomicBool>| {
+            move |_: &World| {
+                started.store(true, Ordering::SeqCst);
+                let begin = std::time::Instant::now();
+                while !other.load(Ordering::SeqCst) {
+                    assert!(begin.elapsed() < std::time::Duration::from_secs(5), "systems did not run in parallel");
+                    std::thread::yield_now();
+                }
+            }
+        };
+        let a = Arc::new(AtomicBool::new(false));
+        let b = Arc::new(AtomicBool::new(false));
+
+        let mut schedule = Schedule::default();
+        schedule.add_system(Stage::Update, "a", wait_for(a.clone(), b.clone())).writes::<Position>();
+        schedule.add_system(Stage::Update, "b", wait_for(b, a)).writes::<Velocity>();
+        schedule.run(&mut World::default());
+    }
+
+    #[test]
+    fn conflicting_or_undeclared_systems_run_alone() {
+        let mut schedule = Schedule::default();
+        schedule.add_system(Stage::Update, "a", |_: &World| {}).writes::<Position>();
+        schedule.add_system(Stage::Update, "b", |_: &World| {}).reads::<Position>();
+        schedule.add_system(Stage::Update, "c", |_: &World| {}).reads::<Velocity>();
+        schedule.add_system(Stage::Update, "d", |_: &World| {}).reads::<Age>().after("c");
+        schedule.add_system(Stage::Update, "e", |_: &World| {}).uses_commands();
+        schedule.add_system(Stage::Update, "f", |_: &World| {}).uses_commands().reads::<Age>();
+        schedule.add_system(Stage::Update, "g", |_: &World| {});
+        schedule.add_system(Stage::Update, "h", |_: &World| {}).reads::<Age>();
+        assert_eq!(schedule.plan()[1], vec![vec![0], vec![1, 2], vec![3, 4], vec![5], vec![6], vec![7]]);
+
+        schedule.set_executor(Executor::SingleThreaded);
+        assert!(schedule.plan()[1].iter().all(|batch| batch.len() == 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "ordering cycle")]
+    fn ordering_cycle_panics() {
+        let mut schedule = Schedule::default();
+        schedule.add_system(Stage::Update, "a", || {}).after("b");
+        schedule.add_system(Stage::Update, "b", || {}).after("a");
+        schedule.run(&mut World::default());
+    }
+}
@@ -0,0 +1,273 @@
+//! Fetching the parameters of systems from the [`World`].
+
+use super::*;
+use std::ops::{Deref, DerefMut};
+
+/// A type that can be used as a parameter of a system.
+///
+/// ```ignore
+/// fn player_killed_system(mut kill_events: EventReader<Kill>, mut player: ResMut<Player>) {
+///     // ...
+/// }
+/// schedule.add_system(Stage::PostUpdate, "player_killed", player_killed_system);
+/// ```
+pub trait SystemParam {
+    /// Data kept by the system between runs.
+    type State: Default + Send + 'static;
+    /// The parameter passed to the system.
+    type Item<'w, 's>;
+
+    /// Declare what the parameter borrows.
+    fn access(access: &mut Access);
+
+    /// Borrow the parameter from the world.
+    fn fetch<'w, 's>(world: &'w World, state: &'s mut Self::State) -> Self::Item<'w, 's>;
+}
+
+/// The item of the [`SystemParam`] `P`.
+pub type SystemParamItem<'w, 's, P> = <P as SystemParam>::Item<'w, 's>;
+
+/// A borrowed resource.
+pub struct Res<'w, R: Send + Sync + 'static> {
+    guard: RwLockReadGuard<'w, R>,
+}
+
+impl<R: Send + Sync + 'static> Deref for Res<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        &self.guard
+    }
+}
+
+impl<R: Send + Sync + 'static> SystemParam for Res<'_, R> {
+    type State = ();
+    type Item<'w, 's> = Res<'w, R>;
+
+    fn access(access: &mut Access) {
+        access.add_resource_read::<R>();
+    }
+
+    fn fetch<'w>(world: &'w World, _state: &mut ()) -> Res<'w, R> {
+        Res { guard: world.resource() }
+    }
+}
+
+/// A mutably borrowed resource.
+pub struct ResMut<'w, R: Send + Sync + 'static> {
+    guard: RwLockWriteGuard<'w, R>,
+}
+
+impl<R: Send + Sync + 'static> Deref for ResMut<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        &self.guard
+    }
+}
+
+impl<R: Send + Sync + 'static> DerefMut for ResMut<'_, R> {
+    fn deref_mut(&mut self) -> &mut R {
+        &mut self.guard
+    }
+}
+
+impl<R: Send + Sync + 'static> SystemParam for ResMut<'_, R> {
+    type State = ();
+    type Item<'w, 's> = ResMut<'w, R>;
+
+    fn access(access: &mut Access) {
+        access.add_resource_write::<R>();
+    }
+
+    fn fetch<'w>(world: &'w World, _state: &mut ()) -> ResMut<'w, R> {
+        ResMut { guard: world.resource_mut() }
+    }
+}
+
+/// A value owned by the system, kept between runs.
+///
+/// Starts as [`Default::default`].
+pub struct Local<'s, T> {
+    value: &'s mut T,
+}
+
+impl<T> Deref for Local<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> DerefMut for Local<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+    }
+}
+
+impl<T: Default + Send + 'static> SystemParam for Local<'_, T> {
+    type State = T;
+    type Item<'w, 's> = Local<'s, T>;
+
+    fn access(_access: &mut Access) {}
+
+    fn fetch<'s>(_world: &World, state: &'s mut T) -> Local<'s, T> {
+        Local { value: state }
+    }
+}
+
+/// Functions that can be registered as a system.
+///
+/// Implemented for functions taking up to eight [`SystemParam`]s, whose
+/// access is declared automatically, and for functions taking `&World`,
+/// which declare nothing.
+pub trait IntoSystem<Params> {
+    /// The function to run and what it borrows, if known.
+    fn into_system(self) -> (SystemFn, Option<Access>);
+}
+
+/// Marker for systems taking the whole `&World`.
+pub struct WorldSystem;
+
+impl<Func: FnMut(&World) + Send + 'static> IntoSystem<WorldSystem> for Func {
+    fn into_system(self) -> (SystemFn, Option<Access>) {
+        (Box::new(self), None)
+    }
+}
+
+/// Functions that can be used as a run condition, see [`System::run_if`].
+///
+/// Implemented for functions taking up to eight [`SystemParam`]s and
+/// returning `bool`, whose access is declared automatically, and for
+/// functions taking `&World`, which declare nothing.
+pub trait IntoRunCondition<Params> {
+    /// The condition to check and what it borrows, if known.
+    fn into_run_condition(self) -> (RunCondition, Option<Access>);
+}
+
+impl<Func: FnMut(&World) -> bool + Send + 'static> IntoRunCondition<WorldSystem> for Func {
+    fn into_run_condition(self) -> (RunCondition, Option<Access>) {
+        (Box::new(self), None)
+    }
+}
+
+macro_rules! system_impl {
+    ($($P:ident),*) => {
+        impl<Func, $($P: SystemParam + 'static),*> IntoSystem<($($P,)*)> for Func
+        where
+            Func: Send + 'static,
+            for<'a> &'a mut Func: FnMut($($P),*) + FnMut($(SystemParamItem<'_, '_, $P>),*),
+        {
+            #[allow(non_snake_case, unused_variables, unused_mut)]
+            fn into_system(mut self) -> (SystemFn, Option<Access>) {
+                let mut access = Access::default();
+                $($P::access(&mut access);)*
+                let mut state: ($($P::State,)*) = Default::default();
+                let run = move |world: &World| {
+                    // Calling through a generic function tells the compiler the exact parameter types.
+                    fn call<$($P),*>(mut f: impl FnMut($($P),*), ($($P,)*): ($($P,)*)) {
+                        f($($P),*)
+                    }
+                    let ($($P,)*) = &mut state;
+                    call(&mut self, ($($P::fetch(world, $P),)*));
+                };
+                (Box::new(run), Some(access))
+            }
+        }
+
+        impl<Func, $($P: SystemParam + 'static),*> IntoRunCondition<($($P,)*)> for Func
+        where
+            Func: Send + 'static,
+            for<'a> &'a mut Func: FnMut($($P),*) -> bool + FnMut($(SystemParamItem<'_, '_, $P>),*) -> bool,
+        {
+            #[allow(non_snake_case, unused_variables, unused_mut)]
+            fn into_run_condition(mut self) -> (RunCondition, Option<Access>) {
+                let mut access = Access::default();
+                $($P::access(&mut access);)*
+                let mut state: ($($P::State,)*) = Default::default();
+                let condition = move |world: &World| {
+                    fn call<$($P),*>(mut f: impl FnMut($($P),*) -> bool, ($($P,)*): ($($P,)*)) -> bool {
+                        f($($P),*)
+                    }
+                    let ($($P,)*) = &mut state;
+                    call(&mut self, ($($P::fetch(world, $P),)*))
+                };
+                (Box::new(condition), Some(access))
+            }
+        }
+    };
+}
+
+system_impl!();
+system_impl!(A);
+system_impl!(A, B);
+system_impl!(A, B, C);
+system_impl!(A, B, C, D);
+system_impl!(A, B, C, D, E);
+system_impl!(A, B, C, D, E, F);
+system_impl!(A, B, C, D, E, F, G);
+system_impl!(A, B, C, D, E, F, G, H);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Counter(u32);
+    impl Component for Counter {}
+
+    #[derive(Default)]
+    struct Total(u32);
+
+    struct Ping(u32);
+
+    #[test]
+    fn params_are_fetched_from_the_world() {
+        let mut world = World::default();
+        world.insert_resource(Total::default());
+        world.insert_resource(Events::<Ping>::default());
+        let entity = world.spawn();
+        world.insert(entity, Counter(0)).unwrap();
+
+        let mut schedule = Schedule::default();
+        schedule.add_system(Stage::Update, "count", |mut query: Query<&mut Counter>, mut runs: Local<u32>, mut pings: EventWriter<Ping>| {
+            *runs += 1;
+            for counter in &mut query {
+                counter.0 += 1;
+            }
+            pings.write(Ping(*runs));
+        });
+        schedule.add_system(Stage::Update, "total", |mut pings: EventReader<Ping>, mut total: ResMut<Total>| total.0 += pings.read().map(|ping| ping.0).sum::<u32>()).after("count");
+        schedule.add_system(Stage::PostUpdate, "spawn", |mut commands: Commands, total: Res<Total>| {
+            commands.spawn().insert(Counter(total.0));
+        });
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+
+        // The local counts the runs and every ping is read once.
+        assert_eq!(world.resource::<Total>().0, 1 + 2);
+        assert_eq!(world.query::<&Counter>().iter().collect::<Vec<_>>(), vec![&Counter(2), &Counter(2), &Counter(3)]);
+    }
+
+    #[test]
+    fn access_is_declared_from_params() {
+        let mut schedule = Schedule::default();
+        let reader = schedule.add_system(Stage::Update, "reader", |_: Query<&Counter>, _: Res<Total>| {}).access().cloned();
+        let writer = schedule.add_system(Stage::Update, "writer", |_: Query<&mut Counter>| {}).access().cloned();
+        let commands = schedule.add_system(Stage::Update, "commands", |_: Commands, _: Local<u32>| {}).access().cloned();
+        let world = schedule.add_system(Stage::Update, "world", |_: &World| {}).access().cloned();
+
+        let (reader, writer, commands) = (reader.unwrap(), writer.unwrap(), commands.unwrap());
+        assert!(!reader.is_compatible(&writer));
+        assert!(reader.is_compatible(&commands));
+        assert!(!commands.is_compatible(&commands));
+        assert!(world.is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Conflicting access")]
+    fn conflicting_params_panic() {
+        Schedule::default().add_system(Stage::Update, "conflict", |_: Res<Total>, _: ResMut<Total>| {});
+    }
+}
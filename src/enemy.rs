@@ -2,43 +2,59 @@
 
 use crate::prelude::*;
 
-/// Spawn a single enemy when runs
-pub fn spawn_enemy_system(arena: &Vec2i32, enemies: &mut HashSet<Entity>, collider_grid: &mut ColliderGrid, entities: &mut Entities, sprites: &mut Components<Sprite>, positions: &mut Components<Vec2i32>, hps: &mut Components<Health>, move_timers: &mut Components<Timer>, damaged_timer: &mut Components<Timer>, damaged_color: &mut Components<Color>) {
-    let enemy_id = entities.spawn();
+/// A marker component for enemies
+pub struct Enemy;
 
-    // Generate a random position within the arena
-    let mut pos = (rand::random_range(-arena.0..=arena.0), rand::random_range(-arena.1..=arena.1));
+impl Component for Enemy {
+    const STORAGE: StorageKind = StorageKind::Marker;
+}
 
-    // Change position if that position already has something in it
-    while collider_grid.get(arena_collider_pos(arena, pos)).is_some() {
-        pos = (rand::random_range(-arena.0..=arena.0), rand::random_range(-arena.1..=arena.1));
-    }
+/// A global resource for the delay between each enemy spawn, a repeating [`Timer`]
+pub struct EnemySpawnTimer(pub Timer);
 
-    // Chance for a special enemy
-    let special = rand::random_bool(0.1);
+/// A global resource for the amount of enemies killed
+#[derive(Default)]
+pub struct Score(pub i32);
+
+/// Tick [`EnemySpawnTimer`] by [`Time::delta`]
+pub fn enemy_spawn_timer_system(time: Res<Time>, mut spawn_timer: ResMut<EnemySpawnTimer>) {
+    spawn_timer.0.tick(time.delta());
+}
 
-    collider_grid.insert(arena_collider_pos(arena, pos), Some(enemy_id));
-    if special {
-        sprites.insert(entities, enemy_id, Sprite { char: '%', style: style::ContentStyle { foreground_color: Some(Color::AnsiValue(75)), ..Default::default() } }).unwrap();
-        move_timers.insert(entities, enemy_id, Timer { current: Duration::ZERO, max: Duration::from_millis(100) }).unwrap();
-    } else {
-        sprites.insert(entities, enemy_id, Sprite { char: '$', style: style::ContentStyle { foreground_color: Some(Color::AnsiValue(218)), ..Default::default() } }).unwrap();
-        move_timers.insert(entities, enemy_id, Timer { current: Duration::ZERO, max: Duration::from_millis(300) }).unwrap();
+/// Spawn a single enemy from the prefabs tagged `enemy` every time [`EnemySpawnTimer`] finishes
+pub fn spawn_enemy_system(mut commands: Commands, arena: Res<Arena>, prefabs: Res<Prefabs>, spawn_timer: Res<EnemySpawnTimer>, mut collider_grid: ResMut<ColliderGrid>, mut rng: ResMut<Rng>) {
+    let arena = arena.0;
+
+    for _ in 0..spawn_timer.0.times_finished_this_tick() {
+        // Generate a random position within the arena
+        let mut pos = rng.arena_pos(arena);
+
+        // Change position if that position already has something in it or is reserved
+        while !collider_grid.reserve(arena_collider_pos(&arena, pos)) {
+            pos = rng.arena_pos(arena);
+        }
+
+        // Pick the kind of enemy by the spawn weights
+        let total_weight = prefabs.total_weight("enemy");
+        if total_weight == 0 {
+            return;
+        }
+        let prefab = prefabs.pick("enemy", rng.range(0..total_weight)).expect("roll is within the total weight");
+
+        let mut enemy = EntityBuilder::default();
+        enemy.bundle(prefab.clone()).with(pos).with(Enemy).with(Collider);
+        commands.spawn_bundle(enemy);
     }
-    positions.insert(entities, enemy_id, pos).unwrap();
-    hps.insert(entities, enemy_id, 10).unwrap();
-    damaged_timer.insert(entities, enemy_id, Timer::new_ended(Duration::from_millis(200))).unwrap();
-    damaged_color.insert(entities, enemy_id, Color::Red).unwrap();
-    enemies.insert(enemy_id);
 }
 
 /// Basic enemy behavior by making it follows the player
-pub fn enemy_follow_system(arena: &Vec2i32, player: &Player, enemies: &HashSet<Entity>, collider_grid: &mut ColliderGrid, damage_events: &mut Events<Damage>, entities: &Entities, positions: &mut Components<Vec2i32>, move_timers: &mut Components<Timer>) {
-    let player_pos = *positions.get(entities, player.id).unwrap();
-    for enemy_id in enemies.iter() {
-        let Ok(enemy_pos) = positions.get_mut(entities, *enemy_id) else { continue };
-        let Ok(timer) = move_timers.get_mut(entities, *enemy_id) else { continue };
-        if timer.current < timer.max {
+///
+/// The moves are applied with the commands at the end of the stage, the new cells are reserved until then.
+pub fn enemy_follow_system(mut commands: Commands, arena: Res<Arena>, mut collider_grid: ResMut<ColliderGrid>, mut damage_events: EventWriter<Damage>, players: Query<(Entity, &Vec2i32), With<PlayerControlled>>, mut enemies: Query<(Entity, &Vec2i32, &mut MoveTimer), With<Enemy>>) {
+    let arena = &arena.0;
+    let Some((player_id, &player_pos)) = players.single() else { return };
+    for (enemy_id, enemy_pos, MoveTimer(timer)) in &mut enemies {
+        if !timer.finished() {
             continue;
         }
         let mut new_pos = *enemy_pos;
@@ -52,31 +68,38 @@ pub fn enemy_follow_system(arena: &Vec2i32, player: &Player, enemies: &HashSet<E
         } else if player_pos.1 < enemy_pos.1 {
             new_pos.1 -= 1;
         }
-        if let Some(id) = collider_grid.get(arena_collider_pos(arena, new_pos)) {
-            if id == player.id {
-                damage_events.push(Damage { target: player.id, amount: 1 });
+        let cell = arena_collider_pos(arena, new_pos);
+        if let Some(id) = collider_grid.get(cell) {
+            if id == player_id {
+                damage_events.write(Damage { target: player_id, amount: 1 });
             } else {
                 continue;
             }
+        } else if collider_grid.reserve(cell) {
+            commands.insert(enemy_id, new_pos);
         } else {
-            collider_grid.remove(arena_collider_pos(arena, *enemy_pos));
-            *enemy_pos = new_pos;
-            collider_grid.insert(arena_collider_pos(arena, new_pos), Some(*enemy_id));
+            continue;
         }
-        timer.current = Duration::ZERO;
+        timer.next_cycle();
     }
 }
 
 /// Cleanup states after the enemy has been killed
-pub fn enemy_killed_system(arena: &Vec2i32, kill_events: &Events<Kill>, collider_grid: &mut ColliderGrid, score: &mut i32, enemies: &mut HashSet<Entity>, entities: &mut Entities, positions: &Components<Vec2i32>) {
-    for dead in kill_events {
-        if !enemies.contains(&dead.target) {
+pub fn enemy_killed_system(mut commands: Commands, mut kill_events: EventReader<Kill>, mut score: ResMut<Score>, enemies: Query<Entity, With<Enemy>>) {
+    for kill in kill_events.read() {
+        if enemies.get(kill.target).is_err() {
             continue;
         }
-        let pos = positions.get(entities, dead.target).unwrap();
-        entities.despawn(dead.target).unwrap();
-        enemies.remove(&dead.target);
-        collider_grid.remove(arena_collider_pos(arena, *pos));
-        *score += 1;
+        commands.despawn_recursive(kill.target);
+        score.0 += 1;
     }
 }
+
+/// Register the enemy systems to the [`Schedule`]
+pub fn add_enemy_systems(schedule: &mut Schedule) {
+    schedule.add_system(Stage::Update, "enemy_spawn_timer", enemy_spawn_timer_system).run_if(playing);
+    schedule.add_system(Stage::Update, "spawn_enemy", spawn_enemy_system).after("enemy_spawn_timer").after("player_movement").run_if(playing);
+    // Runs once the moves of the players are applied so it sees their new position.
+    schedule.add_system(Stage::PostUpdate, "enemy_follow", enemy_follow_system).after("move_timer").before("damage").run_if(playing);
+    schedule.add_system(Stage::PostUpdate, "enemy_killed", enemy_killed_system).after("damage").run_if(playing);
+}
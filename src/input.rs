@@ -2,8 +2,63 @@
 
 use crate::prelude::*;
 
+/// A key of the keyboard
+///
+/// The keys read from the terminal the game can tell apart, other keys
+/// such as modifiers are ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KeyCode {
+    Backspace,
+    Enter,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Tab,
+    BackTab,
+    Delete,
+    Insert,
+    Esc,
+    /// A function key such as `F1`
+    F(u8),
+    /// A character key
+    Char(char),
+}
+
+#[cfg(feature = "terminal")]
+impl KeyCode {
+    /// Convert a key read from the terminal, [`None`] for keys the game ignores
+    pub fn from_terminal(code: event::KeyCode) -> Option<KeyCode> {
+        let key = match code {
+            event::KeyCode::Backspace => KeyCode::Backspace,
+            event::KeyCode::Enter => KeyCode::Enter,
+            event::KeyCode::Left => KeyCode::Left,
+            event::KeyCode::Right => KeyCode::Right,
+            event::KeyCode::Up => KeyCode::Up,
+            event::KeyCode::Down => KeyCode::Down,
+            event::KeyCode::Home => KeyCode::Home,
+            event::KeyCode::End => KeyCode::End,
+            event::KeyCode::PageUp => KeyCode::PageUp,
+            event::KeyCode::PageDown => KeyCode::PageDown,
+            event::KeyCode::Tab => KeyCode::Tab,
+            event::KeyCode::BackTab => KeyCode::BackTab,
+            event::KeyCode::Delete => KeyCode::Delete,
+            event::KeyCode::Insert => KeyCode::Insert,
+            event::KeyCode::Esc => KeyCode::Esc,
+            event::KeyCode::F(number) => KeyCode::F(number),
+            event::KeyCode::Char(char) => KeyCode::Char(char),
+            _ => return None,
+        };
+        Some(key)
+    }
+}
+
 /// A global resource that contains input states.
-#[derive(Default)]
+#[derive(Default, Clone, Debug, PartialEq)]
 pub struct Inputs {
     /// All the keys that is being pressed.
     pub pressed: HashSet<KeyCode>,
@@ -11,31 +66,44 @@ pub struct Inputs {
     pub just_pressed: HashSet<KeyCode>,
     /// All the keys that has *just* been released.
     pub just_released: HashSet<KeyCode>,
+    /// Every key press since the last step in the order they happened, including repeated keys.
+    pub typed: Vec<KeyCode>,
 }
 
-/// Handles terminal input event and store the information inside [`Inputs`] resource.
-pub fn input_system(inputs: &mut Inputs) {
-    // Clear the inputs from last frame.
+/// Handles terminal input events and store the information inside [`Inputs`] resource.
+///
+/// Reads every event since the last step so they do not pile up between steps.
+#[cfg(feature = "terminal")]
+pub fn input_system(mut inputs: ResMut<Inputs>) {
+    // Clear the inputs from last step.
     inputs.just_released.clear();
     inputs.just_pressed.clear();
+    inputs.typed.clear();
 
-    if event::poll(Duration::from_secs(0)).expect("Poll event")
-        && let event::Event::Key(key_event) = event::read().expect("Read event")
-    {
+    while event::poll(Duration::from_secs(0)).expect("Poll event") {
+        let event::Event::Key(key_event) = event::read().expect("Read event") else { continue };
+        let Some(key) = KeyCode::from_terminal(key_event.code) else { continue };
         match key_event.kind {
             event::KeyEventKind::Press => {
-                if !inputs.pressed.contains(&key_event.code) {
-                    inputs.just_pressed.insert(key_event.code);
+                if !inputs.pressed.contains(&key) {
+                    inputs.just_pressed.insert(key);
                 }
-                inputs.pressed.insert(key_event.code);
+                inputs.pressed.insert(key);
+                inputs.typed.push(key);
             }
             event::KeyEventKind::Release => {
-                if inputs.pressed.contains(&key_event.code) {
-                    inputs.just_released.insert(key_event.code);
+                if inputs.pressed.contains(&key) {
+                    inputs.just_released.insert(key);
                 }
-                inputs.pressed.remove(&key_event.code);
+                inputs.pressed.remove(&key);
             }
             _ => {}
         }
     }
 }
+
+/// Register the input systems to the [`Schedule`]
+#[cfg(feature = "terminal")]
+pub fn add_input_systems(schedule: &mut Schedule) {
+    schedule.add_system(Stage::Input, "input", input_system).before("inspector_input");
+}
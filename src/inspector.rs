@@ -0,0 +1,199 @@
+//! Handles the entity inspector
+//!
+//! Press `F1` to pause the game through [`Time::paused`] and inspect the entity under the cursor,
+//! `i`, `j`, `k` and `l` move the cursor, `Up` and `Down` select a field
+//! and `Enter` starts and applies an edit.
+
+use crate::prelude::*;
+
+/// A global resource for the state of the inspector panel
+#[derive(Default)]
+pub struct Inspector {
+    /// Whether the panel is shown, [`Time::paused`] is set while it is
+    pub open: bool,
+    /// The inspected position in the arena
+    pub cursor: Vec2i32,
+    /// The index of the selected field among every field of the entity
+    pub selected: usize,
+    /// The value being typed for the selected field
+    pub input: Option<String>,
+    /// The error of the last edit
+    pub error: Option<String>,
+}
+
+/// Register the components shown by the inspector
+pub fn reflect_registry() -> ReflectRegistry {
+    let mut registry = ReflectRegistry::default();
+    registry.register::<Vec2i32>("Position").register::<Health>("Health").register::<MaxHealth>("MaxHealth").register::<Sprite>("Sprite").register::<MoveTimer>("MoveTimer").register::<WeaponTimer>("WeaponTimer").register::<DamagedTimer>("DamagedTimer");
+    registry
+}
+
+/// The entity with a [`Sprite`] at `pos`
+fn entity_at(world: &World, pos: Vec2i32) -> Option<Entity> {
+    world.query_filtered::<(Entity, &Vec2i32), With<Sprite>>().iter().find(|(_, sprite_pos)| **sprite_pos == pos).map(|(entity, _)| entity)
+}
+
+/// Every field of the entity as its component name, field name and value
+fn entity_fields(world: &World, entity: Entity) -> Vec<(&'static str, &'static str, String)> {
+    let registry = world.resource::<ReflectRegistry>();
+    registry.reflect(world, entity).into_iter().flat_map(|(component, fields)| fields.into_iter().map(move |(field, value)| (component, field, value))).collect()
+}
+
+/// Open and close the inspector, move its cursor and edit the selected field
+///
+/// Takes the whole `&World` since the inspected components are only known at runtime.
+pub fn inspector_input_system(world: &World) {
+    let inputs = world.resource::<Inputs>();
+    let mut inspector = world.resource_mut::<Inspector>();
+    if inputs.just_pressed.contains(&KeyCode::F(1)) {
+        inspector.open = !inspector.open;
+        world.resource_mut::<Time>().paused = inspector.open;
+        inspector.input = None;
+        inspector.error = None;
+        if let Some(&player_pos) = world.query_filtered::<&Vec2i32, With<PlayerControlled>>().iter().next() {
+            inspector.cursor = player_pos;
+        }
+    }
+    if !inspector.open {
+        return;
+    }
+
+    let entity = entity_at(world, inspector.cursor);
+    let fields = entity.map(|entity| entity_fields(world, entity)).unwrap_or_default();
+
+    // Typing a value
+    if let Some(input) = &mut inspector.input {
+        for key in &inputs.typed {
+            match key {
+                KeyCode::Char(char) => input.push(*char),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                _ => {}
+            }
+        }
+        if !inputs.just_pressed.contains(&KeyCode::Enter) {
+            return;
+        }
+        let value = inspector.input.take().expect("Editing");
+        let (Some(entity), Some((component, field, _))) = (entity, fields.get(inspector.selected)) else { return };
+        let registry = world.resource::<ReflectRegistry>();
+        inspector.error = registry.set_field(world, entity, component, field, &value).err().map(|error| error.to_string());
+        return;
+    }
+
+    let mut moved = (0, 0);
+    for (key, dir) in [('i', (0, -1)), ('k', (0, 1)), ('j', (-1, 0)), ('l', (1, 0))] {
+        if inputs.just_pressed.contains(&KeyCode::Char(key)) {
+            moved = (moved.0 + dir.0, moved.1 + dir.1);
+        }
+    }
+    if moved != (0, 0) {
+        inspector.cursor = (inspector.cursor.0 + moved.0, inspector.cursor.1 + moved.1);
+        inspector.selected = 0;
+        inspector.error = None;
+    }
+    if fields.is_empty() {
+        return;
+    }
+    if inputs.just_pressed.contains(&KeyCode::Up) {
+        inspector.selected = (inspector.selected + fields.len() - 1) % fields.len();
+    }
+    if inputs.just_pressed.contains(&KeyCode::Down) {
+        inspector.selected = (inspector.selected + 1) % fields.len();
+    }
+    if inputs.just_pressed.contains(&KeyCode::Enter)
+        && let Some((_, _, value)) = fields.get(inspector.selected)
+    {
+        inspector.input = Some(value.clone());
+        inspector.error = None;
+    }
+}
+
+/// Show the cursor and the fields of the entity under it
+#[cfg(feature = "terminal")]
+pub fn inspector_system(world: &World) {
+    let inspector = world.resource::<Inspector>();
+    if !inspector.open {
+        return;
+    }
+    let stdout = &mut io::stdout().lock();
+    let arena = world.resource::<Arena>().0;
+    let camera_pos = *world.query_filtered::<&Vec2i32, With<Camera>>().single().expect("Camera position");
+    let terminal_size = terminal::size().expect("Terminal size");
+    let terminal_size = (terminal_size.0 as i32, terminal_size.1 as i32);
+    let terminal_middle = (terminal_size.0 / 2, terminal_size.1 / 2);
+
+    let cursor_pos = (terminal_middle.0 + inspector.cursor.0 - camera_pos.0, terminal_middle.1 + inspector.cursor.1 - camera_pos.1);
+    if pos_in_size(cursor_pos, terminal_size) {
+        let _ = queue!(stdout, cursor::MoveTo(cursor_pos.0 as u16, cursor_pos.1 as u16), style::PrintStyledContent('+'.black().on_yellow()));
+    }
+
+    let mut lines = vec![format!("Inspector at ({}, {}), F1 to resume", inspector.cursor.0, inspector.cursor.1)];
+    match entity_at(world, inspector.cursor) {
+        Some(entity) => {
+            lines.push(format!("Entity {entity}"));
+            let mut previous = "";
+            for (index, (component, field, value)) in entity_fields(world, entity).into_iter().enumerate() {
+                if component != previous {
+                    lines.push(component.to_owned());
+                    previous = component;
+                }
+                let selected = index == inspector.selected;
+                let marker = if selected { '>' } else { ' ' };
+                match &inspector.input {
+                    Some(input) if selected => lines.push(format!("{marker} {field}: {input}_")),
+                    _ => lines.push(format!("{marker} {field}: {value}")),
+                }
+            }
+        }
+        None => lines.push("Nothing here".to_owned()),
+    }
+    if let Some(error) = &inspector.error {
+        lines.push(error.clone());
+    }
+
+    // Below the HUD
+    let panel_pos = (terminal_middle.0 - arena.0, terminal_middle.1 + arena.1 + 6);
+    for (y, line) in lines.into_iter().enumerate() {
+        let line_pos = (panel_pos.0, panel_pos.1 + y as i32);
+        if pos_in_size(line_pos, terminal_size) {
+            let _ = queue!(stdout, cursor::MoveTo(line_pos.0 as u16, line_pos.1 as u16), style::Print(line));
+        }
+    }
+}
+
+/// Register the inspector systems to the [`Schedule`]
+pub fn add_inspector_systems(schedule: &mut Schedule) {
+    schedule.add_system(Stage::Input, "inspector_input", inspector_input_system);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn opening_pauses_the_time() {
+        let mut world = World::default();
+        world.insert_resource(reflect_registry());
+        world.insert_resource(Inspector::default());
+        world.insert_resource(Time::default());
+        world.insert_resource(Inputs { just_pressed: [KeyCode::F(1)].into_iter().collect(), ..Default::default() });
+
+        inspector_input_system(&world);
+        assert!(world.resource::<Time>().paused);
+        inspector_input_system(&world);
+        assert!(!world.resource::<Time>().paused);
+    }
+
+    #[test]
+    fn typed_keys_are_added_in_order() {
+        let mut world = World::default();
+        world.insert_resource(reflect_registry());
+        world.insert_resource(Inspector { open: true, input: Some("1".to_owned()), ..Default::default() });
+        world.insert_resource(Inputs { just_pressed: [KeyCode::Char('2'), KeyCode::Backspace].into_iter().collect(), typed: vec![KeyCode::Char('2'), KeyCode::Backspace, KeyCode::Char('2'), KeyCode::Char('0')], ..Default::default() });
+
+        inspector_input_system(&world);
+        assert_eq!(world.resource::<Inspector>().input.as_deref(), Some("120"));
+    }
+}
@@ -0,0 +1,135 @@
+//! A terminal minigame built on a small ECS
+//!
+//! The game logic runs without a terminal, the `terminal` feature (enabled by
+//! default) adds reading the keyboard and rendering to the terminal.
+//!
+//! ```ignore
+//! let mut world = World::default();
+//! setup_world(&mut world, prefabs, Rng::new(seed));
+//! let mut schedule = Schedule::default();
+//! add_game_systems(&mut schedule);
+//! let mut timestep = FixedTimestep::new(TICK_RATE, MAX_TICKS_PER_FRAME);
+//! loop {
+//!     for _ in 0..timestep.advance(elapsed) {
+//!         run_tick(&mut world, &mut schedule, timestep.step);
+//!     }
+//!     run_render(&mut world, &mut schedule);
+//! }
+//! ```
+
+use prelude::*;
+
+pub mod prelude;
+
+pub mod ecs;
+
+pub mod collision;
+pub mod damage;
+pub mod draw;
+pub mod enemy;
+pub mod input;
+pub mod inspector;
+pub mod player;
+pub mod position;
+pub mod prefab;
+pub mod replay;
+pub mod rng;
+pub mod sprite;
+pub mod timer;
+pub mod ui;
+
+pub mod utils;
+
+/// The half size of the arena, see [`Arena`]
+pub const ARENA_EXTEND: Vec2i32 = (30, 10);
+
+/// Insert the events, resources and starting entities of a new game
+///
+/// The same `rng` seed and the same [`Inputs`] every step play out the same game.
+pub fn setup_world(world: &mut World, prefabs: Prefabs, rng: Rng) {
+    // Events
+    world.insert_resource::<Events<Damage>>(Default::default());
+    world.insert_resource::<Events<Kill>>(Default::default());
+    world.insert_resource::<Events<Draw>>(Default::default());
+
+    // Resources
+    world.insert_resource(Inputs::default());
+    world.insert_resource(Time::default());
+    world.insert_resource(ColliderGrid::new(((ARENA_EXTEND.0 * 2 + 1) as usize, (ARENA_EXTEND.1 * 2 + 1) as usize)));
+    world.insert_resource(Score::default());
+    world.insert_resource(prefabs);
+    world.insert_resource(rng);
+    world.insert_resource(Inspector::default());
+    world.insert_resource(reflect_registry());
+    world.insert_resource(EnemySpawnTimer(Timer::repeating(Duration::from_secs(3))));
+
+    // Setup
+    world.insert_resource(Arena(ARENA_EXTEND));
+    add_collider_hooks(world);
+
+    let primary_weapon = Weapon::Stick;
+    world.spawn_bundle(PlayerBundle {
+        player_controlled: PlayerControlled,
+        collider: Collider,
+        weapon_timer: WeaponTimer(Timer::new(primary_weapon.base_delay())),
+        weapon: primary_weapon,
+        move_timer: MoveTimer(Timer::new(Duration::from_millis(50))),
+        sprite: Sprite { char: '@', ..Default::default() },
+        position: (0, 0),
+        health: Health(20),
+        max_health: MaxHealth(20),
+        damaged_timer: DamagedTimer(Timer::new_ended(Duration::from_millis(200))),
+        damaged_color: DamagedColor(Color::Red),
+    });
+
+    let mut camera = EntityBuilder::default();
+    camera.with((0, 0)).with(Camera);
+    camera.spawn(world);
+}
+
+/// Register the gameplay systems to the [`Schedule`]
+///
+/// These run without a terminal, [`Inputs`] has to be filled by hand.
+pub fn add_game_systems(schedule: &mut Schedule) {
+    add_inspector_systems(schedule);
+    add_player_systems(schedule);
+    add_enemy_systems(schedule);
+    add_position_systems(schedule);
+    add_collision_systems(schedule);
+    add_damage_systems(schedule);
+    add_draw_systems(schedule);
+}
+
+/// Register the systems rendering to the terminal
+///
+/// Needs the systems of [`add_game_systems`]. The [`Inputs`] come from
+/// either [`add_input_systems`] or [`add_playback_systems`].
+#[cfg(feature = "terminal")]
+pub fn add_terminal_systems(schedule: &mut Schedule) {
+    add_sprite_systems(schedule);
+    add_ui_systems(schedule);
+}
+
+/// Run one simulation step of `step` real time, every stage but [`Stage::Render`]
+pub fn run_tick(world: &mut World, schedule: &mut Schedule, step: Duration) {
+    // Event update ===================================================================================================
+    world.resource_mut::<Events<Damage>>().update();
+    world.resource_mut::<Events<Kill>>().update();
+    world.resource_mut::<Events<Draw>>().update();
+
+    // Entity maintenance =============================================================================================
+    // Clear the components of entities despawned last frame so their slots can be reused.
+    world.maintain();
+
+    world.resource_mut::<Time>().real_delta = step;
+
+    // Systems ========================================================================================================
+    schedule.run_stages(world, &Stage::SIMULATION);
+
+    world.resource_mut::<Time>().tick += 1;
+}
+
+/// Run the systems of [`Stage::Render`]
+pub fn run_render(world: &mut World, schedule: &mut Schedule) {
+    schedule.run_stages(world, &[Stage::Render]);
+}
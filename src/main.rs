@@ -1,27 +1,71 @@
-#![allow(clippy::too_many_arguments)]
+use rust_minigame::{prelude::*, *};
 
-use prelude::*;
-
-mod prelude;
-
-mod ecs;
-
-mod collision;
-mod damage;
-mod draw;
-mod enemy;
-mod input;
-mod player;
-mod sprite;
-mod timer;
-mod ui;
-
-mod utils;
+/// The frame rate used when `--fps` is not given
+const DEFAULT_FPS: u32 = 60;
 
 fn main() {
     let mut stdout = io::stdout();
 
-    if !terminal::supports_keyboard_enhancement().expect("Check keyboard enhancement") {
+    // Arguments
+    let mut fps = DEFAULT_FPS;
+    let mut seed = None;
+    let mut record_path = None;
+    let mut replay_path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fps" => match args.next().and_then(|value| value.parse().ok()).filter(|fps| *fps >= MIN_FPS) {
+                Some(value) => fps = value,
+                None => {
+                    println!("`--fps` expects a number of frames per second of at least {MIN_FPS}");
+                    return;
+                }
+            },
+            "--seed" => match args.next().and_then(|value| value.parse().ok()) {
+                Some(value) => seed = Some(value),
+                None => {
+                    println!("`--seed` expects a number");
+                    return;
+                }
+            },
+            "--record" | "--replay" => match args.next() {
+                Some(path) if arg == "--record" => record_path = Some(path),
+                Some(path) => replay_path = Some(path),
+                None => {
+                    println!("`{arg}` expects a file path");
+                    return;
+                }
+            },
+            _ => {
+                println!("Unknown argument `{arg}`, usage: rust-minigame [--fps <frames per second>] [--seed <number>] [--record <file>] [--replay <file>]");
+                return;
+            }
+        }
+    }
+    if replay_path.is_some() && (seed.is_some() || record_path.is_some()) {
+        println!("`--replay` plays the seed and inputs of the file, it can not be used with `--seed` or `--record`");
+        return;
+    }
+
+    let replay = match replay_path.map(Replay::load).transpose() {
+        Ok(replay) => replay,
+        Err(error) => {
+            println!("Failed to load replay: {error}");
+            return;
+        }
+    };
+
+    let prefabs = match Prefabs::load(PREFABS_PATH) {
+        Ok(prefabs) => prefabs,
+        Err(PrefabError::Io { error, .. }) if error.kind() == io::ErrorKind::NotFound => Prefabs::parse(PREFABS_PATH, DEFAULT_PREFABS).expect("Parse built-in prefabs"),
+        Err(error) => {
+            println!("Failed to load prefabs: {error}");
+            return;
+        }
+    };
+
+    // Replays only read the keyboard to stop
+    if replay.is_none() && !terminal::supports_keyboard_enhancement().expect("Check keyboard enhancement") {
         println!("Your terminal does not support keyboard enhancement! Please change your terminal.");
         return;
     }
@@ -30,125 +74,84 @@ fn main() {
     terminal::enable_raw_mode().expect("Enable raw mode");
     execute!(&mut stdout, terminal::EnterAlternateScreen, terminal::DisableLineWrap, cursor::Hide, cursor::SavePosition, event::PushKeyboardEnhancementFlags(event::KeyboardEnhancementFlags::all()),).expect("Initialize terminal");
 
-    // Entities
-    let mut entities: Entities = Default::default();
-
-    // Components
-    let mut hps: Components<Health> = Default::default();
-    let mut max_hps: Components<Health> = Default::default();
-    let mut positions: Components<Vec2i32> = Default::default();
-    let mut sprites: Components<Sprite> = Default::default();
-    let mut move_timers: Components<Timer> = Default::default();
-    let mut draw_infos: Components<DrawInfo> = Default::default();
-    let mut draw_timers: Components<Timer> = Default::default();
-    let mut damaged_timers: Components<Timer> = Default::default();
-    let mut damaged_colors: Components<Color> = Default::default();
-
-    // Events
-    let mut damage_events: Events<Damage> = Default::default();
-    let mut kill_events: Events<Kill> = Default::default();
-    let mut spawn_draw_events: Events<Draw> = Default::default();
-
-    // Resources
-    let mut inputs: Inputs = Default::default();
-    let mut enemies: HashSet<Entity> = Default::default();
-    let mut collider_grid: ColliderGrid = Default::default();
-    let mut score: i32 = 0;
-
-    // Setup
-    let player_id = entities.spawn();
-    sprites.insert(&entities, player_id, Sprite { char: '@', ..Default::default() }).unwrap();
-    positions.insert(&entities, player_id, (0, 0)).unwrap();
-    hps.insert(&entities, player_id, 20).unwrap();
-    max_hps.insert(&entities, player_id, 20).unwrap();
-    damaged_timers.insert(&entities, player_id, Timer::new_ended(Duration::from_millis(200))).unwrap();
-    damaged_colors.insert(&entities, player_id, Color::Red).unwrap();
-    let player = Player { id: player_id, primary_weapon: Weapon::Stick };
-
-    let camera_id = entities.spawn();
-    positions.insert(&entities, camera_id, (0, 0)).unwrap();
-
-    let arena_extend = (30i32, 10i32);
-    for _ in 0..arena_extend.0 * 2 + 1 {
-        collider_grid.0.push(vec![None; (arena_extend.1 * 2 + 1) as usize]);
+    // Entities, components and resources
+    let mut world = World::default();
+    let seed = replay.as_ref().map(|replay| replay.seed).or(seed);
+    let rng = seed.map(Rng::new).unwrap_or_else(Rng::from_random_seed);
+    let seed = rng.seed();
+    setup_world(&mut world, prefabs, rng);
+    if record_path.is_some() {
+        world.insert_resource(Recording(Replay { seed, frames: Vec::new() }));
+    }
+    let replaying = replay.is_some();
+    if let Some(replay) = replay {
+        world.insert_resource(Playback::new(replay));
     }
 
-    execute!(&mut stdout, terminal::SetSize((arena_extend.0 * 2 + 1 + 50) as u16, (arena_extend.1 * 2 + 1 + 50) as u16)).unwrap();
-
-    let mut player_dead = false;
+    execute!(&mut stdout, terminal::SetSize((ARENA_EXTEND.0 * 2 + 1 + 50) as u16, (ARENA_EXTEND.1 * 2 + 1 + 50) as u16)).unwrap();
 
-    let mut move_timer = Timer::new(Duration::from_millis(50));
-    let mut spawn_enemy_timer = Timer::new(Duration::from_secs(3));
-    let mut weapon_timer = Timer::new(player.primary_weapon.base_delay());
+    // Systems, commands are applied after every stage
+    let mut schedule = Schedule::default();
+    add_game_systems(&mut schedule);
+    if replaying {
+        add_playback_systems(&mut schedule);
+    } else {
+        add_input_systems(&mut schedule);
+    }
+    if record_path.is_some() {
+        add_recording_systems(&mut schedule);
+    }
+    add_terminal_systems(&mut schedule);
 
+    // The simulation runs at a fixed rate, the rendering at most `fps` times per second
+    let mut timestep = FixedTimestep::new(TICK_RATE, MAX_TICKS_PER_FRAME);
+    let frame_time = Duration::from_secs(1) / fps;
     let mut prev_instant = std::time::Instant::now();
     loop {
-        // Event intialization ========================================================================================
-        damage_events.clear();
-        kill_events.clear();
-        spawn_draw_events.clear();
-
-        // Delta time calculation ======================================================================================
+        // Elapsed time calculation ====================================================================================
         let next_instant = std::time::Instant::now();
-        let delta = next_instant - prev_instant;
-        prev_instant = std::time::Instant::now();
-
-        // Systems ====================================================================================================
-
-        input_system(&mut inputs);
-
-        if inputs.pressed.contains(&KeyCode::Esc) {
-            break;
-        }
-
-        timer_system(delta, &entities, &mut draw_timers);
-
-        if !player_dead {
-            move_timer.current += delta;
-            spawn_enemy_timer.current += delta;
-            weapon_timer.current += delta;
-            timer_system(delta, &entities, &mut move_timers);
-            timer_system(delta, &entities, &mut damaged_timers);
-
-            // Spawn an enemy at every some amount of time
-            if spawn_enemy_timer.finished() {
-                spawn_enemy_system(&arena_extend, &mut enemies, &mut collider_grid, &mut entities, &mut sprites, &mut positions, &mut hps, &mut move_timers, &mut damaged_timers, &mut damaged_colors);
-                spawn_enemy_timer.reset();
+        let elapsed = next_instant - prev_instant;
+        prev_instant = next_instant;
+
+        // Quit on the step `Esc` is pressed so replays stop at the same step
+        let mut quit = false;
+        for _ in 0..timestep.advance(elapsed) {
+            run_tick(&mut world, &mut schedule, timestep.step);
+            if world.resource::<Inputs>().pressed.contains(&KeyCode::Esc) {
+                quit = true;
+                break;
             }
-
-            player_movement_system(&mut move_timer, &arena_extend, player_id, &inputs, &mut collider_grid, &mut entities, &mut positions);
-            player_weapon_system(&arena_extend, &player, &mut weapon_timer, &collider_grid, &mut spawn_draw_events, &mut damage_events, &entities, &inputs, &positions);
-
-            enemy_follow_system(&arena_extend, &player, &enemies, &mut collider_grid, &mut damage_events, &entities, &mut positions, &mut move_timers);
-            damage_system(&mut stdout, &damage_events, &mut kill_events, &entities, &mut hps, &mut damaged_timers);
-            enemy_killed_system(&arena_extend, &kill_events, &mut collider_grid, &mut score, &mut enemies, &mut entities, &positions);
-            player_killed_system(&kill_events, &mut player_dead, &player);
         }
-
-        spawn_draw_system(&spawn_draw_events, &mut entities, &mut positions, &mut draw_infos, &mut draw_timers);
-
-        // Rendering  ----------------------------------------------------------------------------------------------
-
-        // Lock the stdout just in case. It can cause bottleneck sometimes without this.
-        let mut stdout = stdout.lock();
-
-        // Clear the terminal
-        // And syncronize to prevent tearing
-        queue!(&mut stdout, terminal::BeginSynchronizedUpdate, terminal::Clear(terminal::ClearType::Purge)).unwrap();
-
-        draw_system(&mut stdout, camera_id, &mut entities, &positions, &mut draw_infos, &draw_timers);
-        sprite_system(&mut stdout, camera_id, &entities, &positions, &sprites, &damaged_timers, &damaged_colors);
-        visualize_arena_wall_system(&mut stdout, &arena_extend, camera_id, &entities, &positions);
-        hud_system(&mut stdout, &arena_extend, &score, &player, &entities, &hps, &max_hps);
-        if player_dead {
-            display_end_screen_system(&mut stdout, &score);
+        if quit || (replaying && esc_pressed()) {
+            break;
         }
+        run_render(&mut world, &mut schedule);
 
-        queue!(&mut stdout, terminal::EndSynchronizedUpdate, cursor::RestorePosition).unwrap();
-        stdout.flush().expect("Flush stdout");
+        // Sleep for the rest of the frame
+        std::thread::sleep(frame_time.saturating_sub(next_instant.elapsed()));
     }
 
     // Clean up
     terminal::disable_raw_mode().expect("Disable raw mode");
     execute!(stdout, terminal::LeaveAlternateScreen, cursor::Show, event::PopKeyboardEnhancementFlags).expect("Cleanup terminal");
+    println!("Seed: {}", world.resource::<Rng>().seed());
+    if let Some(path) = record_path {
+        match world.resource::<Recording>().0.save(&path) {
+            Ok(()) => println!("Replay saved to {path}"),
+            Err(error) => println!("Failed to save replay to {path}: {error}"),
+        }
+    }
+}
+
+/// Read the pending terminal events and check if `Esc` was pressed, used to stop replays
+fn esc_pressed() -> bool {
+    let mut pressed = false;
+    while event::poll(Duration::ZERO).expect("Poll event") {
+        if let event::Event::Key(key_event) = event::read().expect("Read event")
+            && key_event.code == event::KeyCode::Esc
+        {
+            pressed = true;
+        }
+    }
+    pressed
 }
@@ -2,20 +2,71 @@
 
 use crate::prelude::*;
 
-/// Global resource about main player
-pub struct Player {
-    /// The player's entity id
-    pub id: Entity,
-    /// The primary weapon
-    pub primary_weapon: Weapon,
+/// A marker component for the entities moved and attacking by the [`Inputs`]
+pub struct PlayerControlled;
+
+impl Component for PlayerControlled {
+    const STORAGE: StorageKind = StorageKind::Marker;
+}
+
+/// A marker component for players that have been killed
+pub struct Dead;
+
+impl Component for Dead {
+    const STORAGE: StorageKind = StorageKind::Marker;
+}
+
+/// A component for the delay between each weapon attack
+#[derive(Clone)]
+pub struct WeaponTimer(pub Timer);
+
+impl Component for WeaponTimer {}
+
+impl AsMut<Timer> for WeaponTimer {
+    fn as_mut(&mut self) -> &mut Timer {
+        &mut self.0
+    }
+}
+
+impl Reflect for WeaponTimer {
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        self.0.fields()
+    }
+
+    fn set_field(&mut self, name: &str, value: &str) -> Result<(), ReflectError> {
+        self.0.set_field(name, value)
+    }
 }
 
-/// Weapons
+/// Components of the player entity
+pub struct PlayerBundle {
+    pub player_controlled: PlayerControlled,
+    pub collider: Collider,
+    pub weapon: Weapon,
+    pub weapon_timer: WeaponTimer,
+    pub move_timer: MoveTimer,
+    pub sprite: Sprite,
+    pub position: Vec2i32,
+    pub health: Health,
+    pub max_health: MaxHealth,
+    pub damaged_timer: DamagedTimer,
+    pub damaged_color: DamagedColor,
+}
+
+impl Bundle for PlayerBundle {
+    fn build(self, builder: &mut EntityBuilder) {
+        builder.with(self.player_controlled).with(self.collider).with(self.weapon).with(self.weapon_timer).with(self.move_timer).with(self.sprite).with(self.position).with(self.health).with(self.max_health).with(self.damaged_timer).with(self.damaged_color);
+    }
+}
+
+/// A component for the weapon an entity attacks with
 pub enum Weapon {
     /// Default weapon
     Stick,
 }
 
+impl Component for Weapon {}
+
 impl Weapon {
     /// Get base delay for each weapon
     pub fn base_delay(&self) -> Duration {
@@ -40,10 +91,24 @@ impl std::fmt::Display for Weapon {
     }
 }
 
+/// Run condition for systems that only run while a player is alive and the game is not paused
+pub fn playing(time: Res<Time>, players: Query<Entity, (With<PlayerControlled>, Without<Dead>)>) -> bool {
+    not_paused(time) && !game_over(players)
+}
+
+/// Run condition for systems that only run once every player has died
+pub fn game_over(players: Query<Entity, (With<PlayerControlled>, Without<Dead>)>) -> bool {
+    players.iter().next().is_none()
+}
+
 /// Basic WASD player movement.
-pub fn player_movement_system(move_timer: &mut Timer, arena: &Vec2i32, player_id: Entity, inputs: &Inputs, collider_grid: &mut ColliderGrid, entities: &mut Entities, positions: &mut Components<Vec2i32>) {
+///
+/// The move is applied with the commands at the end of the stage, the new cell is reserved until then.
+pub fn player_movement_system(mut commands: Commands, arena: Res<Arena>, inputs: Res<Inputs>, mut collider_grid: ResMut<ColliderGrid>, mut players: Query<(Entity, &Vec2i32, &mut MoveTimer), With<PlayerControlled>>) {
+    let arena = &arena.0;
+    let Some((player_id, player_pos, MoveTimer(move_timer))) = players.single_mut() else { return };
+
     let mut moved = false;
-    let player_pos = positions.get_mut(entities, player_id).unwrap();
     let mut new_pos = *player_pos;
     // move_timer make sures the player doesn't move too fast
     if move_timer.finished() && inputs.pressed.contains(&KeyCode::Char('w')) && player_pos.1 > -arena.1 {
@@ -63,18 +128,19 @@ pub fn player_movement_system(move_timer: &mut Timer, arena: &Vec2i32, player_id
         moved = true;
     }
     if moved {
-        move_timer.reset();
+        move_timer.next_cycle();
     }
-    if collider_grid.get(arena_collider_pos(arena, new_pos)).is_none() {
-        collider_grid.remove(arena_collider_pos(arena, *player_pos));
-        *player_pos = new_pos;
-        collider_grid.insert(arena_collider_pos(arena, new_pos), Some(player_id));
+    if moved && collider_grid.reserve(arena_collider_pos(arena, new_pos)) {
+        commands.insert(player_id, new_pos);
     }
 }
 
 /// Weapon system
-pub fn player_weapon_system(arena: &Vec2i32, player: &Player, weapon_timer: &mut Timer, collider_grid: &ColliderGrid, draw_events: &mut Events<Draw>, damage_events: &mut Events<Damage>, entities: &Entities, inputs: &Inputs, positions: &Components<Vec2i32>) {
-    let player_pos = *positions.get(entities, player.id).unwrap();
+///
+/// Runs after the moves of [`Stage::Update`] are applied, so the attack is checked where it is drawn.
+pub fn player_weapon_system(arena: Res<Arena>, inputs: Res<Inputs>, collider_grid: Res<ColliderGrid>, mut draw_events: EventWriter<Draw>, mut damage_events: EventWriter<Damage>, mut players: Query<(Entity, &Vec2i32, &Weapon, &mut WeaponTimer), With<PlayerControlled>>) {
+    let arena = &arena.0;
+    let Some((player_id, &player_pos, weapon, WeaponTimer(weapon_timer))) = players.single_mut() else { return };
 
     // Control the direction in which the weapon is activated
     let mut dir = (0, 0);
@@ -90,35 +156,50 @@ pub fn player_weapon_system(arena: &Vec2i32, player: &Player, weapon_timer: &mut
     if inputs.pressed.contains(&KeyCode::Right) {
         dir.0 += 1
     }
-    if (dir.0 == 0 && dir.1 == 0) || !weapon_timer.finished() {
+    if dir.0 == 0 && dir.1 == 0 {
         return;
     }
-    match player.primary_weapon {
-        Weapon::Stick => {
-            let attack_mid = (player_pos.0 + dir.0 * 2, player_pos.1 + dir.1 * 2);
-            let attack_top_left = (attack_mid.0 - 1, attack_mid.1 - 1);
-            // Area of attack effect
-            draw_events.push(Draw { draw_info: DrawInfo { sprite: Sprite { char: '.', ..Default::default() }, shape: Shape::Rectangle { w: 3, h: 3 } }, position: attack_top_left, timer: Timer::new(Duration::from_millis(50)) });
-            for x in 0..3 {
-                for y in 0..3 {
-                    let found = collider_grid.get(arena_collider_pos(arena, (attack_top_left.0 + x, attack_top_left.1 + y)));
-                    if let Some(id) = found
-                        && id != player.id
-                    {
-                        damage_events.push(Damage { target: id, amount: player.primary_weapon.base_damage() });
+    // A step longer than the delay can finish more than one cycle.
+    while weapon_timer.finished() {
+        match weapon {
+            Weapon::Stick => {
+                let attack_mid = (player_pos.0 + dir.0 * 2, player_pos.1 + dir.1 * 2);
+                let attack_top_left = (attack_mid.0 - 1, attack_mid.1 - 1);
+                // Area of attack effect, following the player while it lasts
+                let relative_top_left = (attack_top_left.0 - player_pos.0, attack_top_left.1 - player_pos.1);
+                draw_events.write(Draw { draw_info: DrawInfo { sprite: Sprite { char: '.', ..Default::default() }, shape: Shape::Rectangle { w: 3, h: 3 } }, position: relative_top_left, parent: Some(player_id), timer: Timer::new(Duration::from_millis(50)) });
+                for x in 0..3 {
+                    for y in 0..3 {
+                        let found = collider_grid.get(arena_collider_pos(arena, (attack_top_left.0 + x, attack_top_left.1 + y)));
+                        if let Some(id) = found
+                            && id != player_id
+                        {
+                            damage_events.write(Damage { target: id, amount: weapon.base_damage() });
+                        }
                     }
                 }
             }
         }
+        weapon_timer.next_cycle();
+        if weapon_timer.max.is_zero() {
+            break;
+        }
     }
-    weapon_timer.reset();
 }
 
-/// Detects player dying and set `player_dead` state
-pub fn player_killed_system(kill_events: &Events<Kill>, player_dead: &mut bool, player: &Player) {
-    for kill in kill_events {
-        if player.id == kill.target {
-            *player_dead = true;
+/// Detects players dying and mark them [`Dead`]
+pub fn player_killed_system(mut commands: Commands, mut kill_events: EventReader<Kill>, players: Query<Entity, With<PlayerControlled>>) {
+    for kill in kill_events.read() {
+        if players.get(kill.target).is_ok() {
+            commands.insert(kill.target, Dead);
         }
     }
 }
+
+/// Register the player systems to the [`Schedule`]
+pub fn add_player_systems(schedule: &mut Schedule) {
+    schedule.add_system(Stage::Update, "weapon_timer", timer_system::<WeaponTimer>).run_if(playing);
+    schedule.add_system(Stage::Update, "player_movement", player_movement_system).after("move_timer").run_if(playing);
+    schedule.add_system(Stage::PostUpdate, "player_weapon", player_weapon_system).after("sync_collider_grid").before("damage").before("spawn_draw").run_if(playing);
+    schedule.add_system(Stage::PostUpdate, "player_killed", player_killed_system).after("damage").run_if(playing);
+}
@@ -0,0 +1,87 @@
+//! Handles movement and positions relative to a parent
+
+use crate::prelude::*;
+
+/// A component for the delay between each movement
+#[derive(Clone)]
+pub struct MoveTimer(pub Timer);
+
+impl Component for MoveTimer {}
+
+impl AsMut<Timer> for MoveTimer {
+    fn as_mut(&mut self) -> &mut Timer {
+        &mut self.0
+    }
+}
+
+impl Reflect for MoveTimer {
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        self.0.fields()
+    }
+
+    fn set_field(&mut self, name: &str, value: &str) -> Result<(), ReflectError> {
+        self.0.set_field(name, value)
+    }
+}
+
+/// A component for the position relative to the [`Parent`]
+///
+/// The [`Vec2i32`] of the entity is set from it every frame.
+pub struct RelativePosition(pub Vec2i32);
+
+impl Component for RelativePosition {}
+
+/// Set the position of every child with [`RelativePosition`] from the position of its parent
+///
+/// Only the positions that moved are inserted, so the others are not marked as changed.
+pub fn propagate_position_system(mut commands: Commands, roots: Query<(Entity, &Children), Without<Parent>>, children: Query<&Children>, relative_positions: Query<&RelativePosition>, positions: Query<&Vec2i32>) {
+    for (root, root_children) in roots.iter() {
+        let Ok(&root_pos) = positions.get(root) else { continue };
+        // Parents are always set before their children.
+        let mut stack = root_children.iter().map(|child| (child, root_pos)).collect::<Vec<_>>();
+        while let Some((entity, parent_pos)) = stack.pop() {
+            let Ok(RelativePosition(offset)) = relative_positions.get(entity) else { continue };
+            let Ok(&old_pos) = positions.get(entity) else { continue };
+            let pos = (parent_pos.0 + offset.0, parent_pos.1 + offset.1);
+            if pos != old_pos {
+                commands.insert(entity, pos);
+            }
+            if let Ok(grandchildren) = children.get(entity) {
+                stack.extend(grandchildren.iter().map(|child| (child, pos)));
+            }
+        }
+    }
+}
+
+/// Register the position systems to the [`Schedule`]
+pub fn add_position_systems(schedule: &mut Schedule) {
+    schedule.add_system(Stage::Update, "move_timer", timer_system::<MoveTimer>).run_if(playing);
+    schedule.add_system(Stage::PostUpdate, "propagate_position", propagate_position_system);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_moved_children_are_changed() {
+        let mut world = World::default();
+        let root = world.spawn();
+        world.insert(root, (5, 5)).unwrap();
+        let child = world.spawn();
+        world.insert(child, (0, 0)).unwrap();
+        world.insert(child, RelativePosition((1, 0))).unwrap();
+        world.set_parent(child, root).unwrap();
+        let mut schedule = Schedule::default();
+        schedule.add_system(Stage::PostUpdate, "propagate_position", propagate_position_system);
+
+        world.maintain();
+        schedule.run(&mut world);
+        assert_eq!(world.query::<&Vec2i32>().get(child), Ok(&(6, 5)));
+
+        world.maintain();
+        schedule.run(&mut world);
+        let positions = world.components::<Vec2i32>();
+        assert_eq!(positions.changed_since(world.entities(), world.change_tick()).count(), 0);
+    }
+}
@@ -0,0 +1,231 @@
+//! Handles entity prefabs loaded from text files
+//!
+//! A prefab file is a list of `[name]` sections, each followed by
+//! `key = value` lines. Lines starting with `#` are comments.
+//!
+//! ```text
+//! [enemy]
+//! char = $
+//! color = 218
+//! health = 10
+//! move_timer = 300ms
+//! tags = enemy
+//! spawn_weight = 9
+//! ```
+
+use crate::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// The file the game loads its prefabs from at startup
+pub const PREFABS_PATH: &str = "assets/prefabs.txt";
+
+/// The prefabs built into the binary, used when [`PREFABS_PATH`] does not exist
+pub const DEFAULT_PREFABS: &str = include_str!("../assets/prefabs.txt");
+
+/// An entity description, spawned as a [`Bundle`]
+///
+/// Only the components that are set are inserted.
+#[derive(Clone, Default)]
+pub struct Prefab {
+    /// The name of the section
+    pub name: String,
+    /// The [`Sprite`] char
+    pub char: Option<char>,
+    /// The [`Sprite`] color
+    pub color: Option<Color>,
+    /// The starting [`Health`]
+    pub health: Option<i32>,
+    /// The [`MaxHealth`]
+    pub max_health: Option<i32>,
+    /// The delay between each move, see [`MoveTimer`]
+    pub move_timer: Option<Duration>,
+    /// How long [`DamagedColor`] is displayed, see [`DamagedTimer`]
+    pub damaged_timer: Option<Duration>,
+    /// The [`DamagedColor`]
+    pub damaged_color: Option<Color>,
+    /// Words used to find prefabs, such as `enemy`
+    pub tags: Vec<String>,
+    /// How often the prefab is picked compared to others with the same tag
+    pub spawn_weight: u32,
+}
+
+impl Prefab {
+    /// Set a field from a `key = value` line
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "char" => {
+                let mut chars = value.chars();
+                let (Some(char), None) = (chars.next(), chars.next()) else { return Err(format!("`char` must be a single character, found `{value}`")) };
+                self.char = Some(char);
+            }
+            "color" => self.color = Some(parse_color(value)?),
+            "health" => self.health = Some(value.parse().map_err(|_| format!("invalid health `{value}`"))?),
+            "max_health" => self.max_health = Some(value.parse().map_err(|_| format!("invalid max health `{value}`"))?),
+            "move_timer" => self.move_timer = Some(parse_duration(value)?),
+            "damaged_timer" => self.damaged_timer = Some(parse_duration(value)?),
+            "damaged_color" => self.damaged_color = Some(parse_color(value)?),
+            "tags" => self.tags = value.split_whitespace().map(str::to_owned).collect(),
+            "spawn_weight" => self.spawn_weight = value.parse().map_err(|_| format!("invalid spawn weight `{value}`"))?,
+            _ => return Err(format!("unknown key `{key}`")),
+        }
+        Ok(())
+    }
+
+    /// Check if the prefab has the tag
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+impl Bundle for Prefab {
+    fn build(self, builder: &mut EntityBuilder) {
+        if let Some(char) = self.char {
+            builder.with(Sprite { char, color: self.color });
+        }
+        if let Some(health) = self.health {
+            builder.with(Health(health));
+        }
+        if let Some(max_health) = self.max_health {
+            builder.with(MaxHealth(max_health));
+        }
+        if let Some(max) = self.move_timer {
+            builder.with(MoveTimer(Timer::new(max)));
+        }
+        if let Some(max) = self.damaged_timer {
+            builder.with(DamagedTimer(Timer::new_ended(max)));
+        }
+        if let Some(color) = self.damaged_color {
+            builder.with(DamagedColor(color));
+        }
+    }
+}
+
+/// Errors returned when loading prefabs
+#[derive(Debug)]
+pub enum PrefabError {
+    /// The file could not be read
+    Io {
+        /// The file
+        path: PathBuf,
+        /// The reason
+        error: io::Error,
+    },
+    /// A line could not be parsed
+    Parse {
+        /// The file
+        path: PathBuf,
+        /// The line number, starting from 1
+        line: usize,
+        /// The reason
+        message: String,
+    },
+}
+
+impl std::error::Error for PrefabError {}
+
+impl std::fmt::Display for PrefabError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PrefabError::Io { path, error } => write!(f, "{}: {error}", path.display()),
+            PrefabError::Parse { path, line, message } => write!(f, "{}:{line}: {message}", path.display()),
+        }
+    }
+}
+
+/// A global resource for every loaded prefab, in the order they are defined
+#[derive(Default)]
+pub struct Prefabs(pub Vec<Prefab>);
+
+impl Prefabs {
+    /// Read and parse a prefab file
+    pub fn load(path: impl AsRef<Path>) -> Result<Prefabs, PrefabError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|error| PrefabError::Io { path: path.to_owned(), error })?;
+        Prefabs::parse(path, &source)
+    }
+
+    /// Parse the prefabs in `source`, `path` is only used for errors
+    pub fn parse(path: impl AsRef<Path>, source: &str) -> Result<Prefabs, PrefabError> {
+        let mut prefabs: Vec<Prefab> = Vec::new();
+        for (index, line) in source.lines().enumerate() {
+            let error = |message| PrefabError::Parse { path: path.as_ref().to_owned(), line: index + 1, message };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[') {
+                let Some(name) = name.strip_suffix(']').map(str::trim) else { return Err(error("missing `]` after the prefab name".to_owned())) };
+                if name.is_empty() {
+                    return Err(error("empty prefab name".to_owned()));
+                }
+                if prefabs.iter().any(|prefab| prefab.name == name) {
+                    return Err(error(format!("prefab `{name}` is already defined")));
+                }
+                prefabs.push(Prefab { name: name.to_owned(), ..Default::default() });
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else { return Err(error(format!("expected `[name]` or `key = value`, found `{line}`"))) };
+            let Some(prefab) = prefabs.last_mut() else { return Err(error(format!("`{}` is set before any `[name]`", key.trim()))) };
+            prefab.set(key.trim(), value.trim()).map_err(error)?;
+        }
+        Ok(Prefabs(prefabs))
+    }
+
+    /// The sum of [`Prefab::spawn_weight`] of every prefab with the tag
+    pub fn total_weight(&self, tag: &str) -> u32 {
+        self.0.iter().filter(|prefab| prefab.has_tag(tag)).map(|prefab| prefab.spawn_weight).sum()
+    }
+
+    /// Pick a prefab with the tag by its weight
+    ///
+    /// `roll` should be random from zero to [`Prefabs::total_weight`].
+    pub fn pick(&self, tag: &str, mut roll: u32) -> Option<&Prefab> {
+        self.0.iter().filter(|prefab| prefab.has_tag(tag)).find(|prefab| {
+            if roll < prefab.spawn_weight {
+                return true;
+            }
+            roll -= prefab.spawn_weight;
+            false
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_prefabs() {
+        let source = "# Enemies\n[enemy]\nchar = $\ncolor = 218\nhealth = 10\nmove_timer = 300ms\ndamaged_color = dark_red\ntags = enemy slow\nspawn_weight = 9\n\n[boss]\nchar = #\nmove_timer = 2s\ntags = enemy\nspawn_weight = 1\n";
+        let prefabs = Prefabs::parse("test.txt", source).unwrap();
+        let get = |name| prefabs.0.iter().find(|prefab| prefab.name == name).unwrap();
+
+        let enemy = get("enemy");
+        assert_eq!(enemy.char, Some('$'));
+        assert_eq!(enemy.color, Some(Color::AnsiValue(218)));
+        assert_eq!(enemy.health, Some(10));
+        assert_eq!(enemy.move_timer, Some(Duration::from_millis(300)));
+        assert_eq!(enemy.damaged_color, Some(Color::DarkRed));
+        assert_eq!(enemy.tags, vec!["enemy", "slow"]);
+        assert_eq!(get("boss").move_timer, Some(Duration::from_secs(2)));
+
+        assert_eq!(prefabs.total_weight("enemy"), 10);
+        assert_eq!(prefabs.pick("enemy", 8).unwrap().name, "enemy");
+        assert_eq!(prefabs.pick("enemy", 9).unwrap().name, "boss");
+        assert!(prefabs.pick("slow", 9).is_none());
+    }
+
+    #[test]
+    fn parse_errors_point_to_the_line() {
+        let error = |source| Prefabs::parse("enemies.txt", source).err().unwrap().to_string();
+
+        assert_eq!(error("char = $"), "enemies.txt:1: `char` is set before any `[name]`");
+        assert_eq!(error("[enemy]\n\nhealth = ten"), "enemies.txt:3: invalid health `ten`");
+        assert_eq!(error("[enemy]\nmove_timer = 300"), "enemies.txt:2: invalid duration `300`, expected something like `300ms` or `2s`");
+        assert_eq!(error("[enemy]\ncolor = pink"), "enemies.txt:2: invalid color `pink`");
+        assert_eq!(error("[enemy]\nspeed = 3"), "enemies.txt:2: unknown key `speed`");
+        assert_eq!(error("[enemy]\n[enemy]"), "enemies.txt:2: prefab `enemy` is already defined");
+        assert_eq!(error("[enemy"), "enemies.txt:1: missing `]` after the prefab name");
+        assert!(matches!(Prefabs::load("missing/prefabs.txt"), Err(PrefabError::Io { .. })));
+    }
+}
@@ -1,8 +1,17 @@
 //! This modules include commonly used imports used across all modules
 
-pub use crate::{collision::*, damage::*, draw::*, ecs::*, enemy::*, input::*, player::*, sprite::*, timer::*, ui::*, utils::*};
-pub use crossterm::{cursor, event::{self, KeyCode}, execute, queue, style::{self, Color, Stylize}, terminal};
-pub use std::{collections::HashSet, io::{self, Stdout, StdoutLock, Write}, time::Duration};
+pub use crate::{collision::*, damage::*, draw::*, ecs::*, enemy::*, input::*, inspector::*, player::*, position::*, prefab::*, replay::*, rng::*, sprite::*, timer::*, ui::*, utils::*};
+#[cfg(feature = "terminal")]
+pub use crossterm::{
+    cursor, event, execute, queue,
+    style::{self, Stylize},
+    terminal,
+};
+pub use std::{
+    collections::HashSet,
+    io::{self, Write},
+    time::Duration,
+};
 
 // Commonly used 2D vector types
 
@@ -10,4 +19,20 @@ pub use std::{collections::HashSet, io::{self, Stdout, StdoutLock, Write}, time:
 pub type Vec2i32 = (i32, i32);
 /// A Vec2 of usize
 pub type Vec2usize = (usize, usize);
-pub type Health = i32;
+
+impl Component for Vec2i32 {}
+
+impl Reflect for Vec2i32 {
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![("x", self.0.to_string()), ("y", self.1.to_string())]
+    }
+
+    fn set_field(&mut self, name: &str, value: &str) -> Result<(), ReflectError> {
+        match name {
+            "x" => self.0 = parse_field(name, value)?,
+            "y" => self.1 = parse_field(name, value)?,
+            _ => return Err(ReflectError::UnknownField(name.to_owned())),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,247 @@
+//! Handles recording and replaying the inputs of a game
+//!
+//! A replay file starts with the [`Rng`] seed, followed by the [`Inputs`] of
+//! every step where a key was held, pressed or released. Each line is the
+//! step number then the `pressed`, `just_pressed` and `just_released` keys
+//! separated by `|`, see [`parse_key`]. Steps where keys were typed end with
+//! one more `|` and the `typed` keys in the order they were pressed. Lines
+//! starting with `#` are comments.
+//!
+//! ```text
+//! seed = 1234
+//! 120 w | w | | w
+//! 121 w Up | Up | | Up
+//! 122 | | w Up
+//! 123 F1 | F1 | | F1 F1
+//! ```
+//!
+//! Since the game runs at a fixed [`TICK_RATE`], the same seed and inputs
+//! play out the same game. The seed is only meaningful to the ChaCha8
+//! generator of [`Rng`], which gives the same values on every platform, so
+//! replays can be shared between machines.
+
+use crate::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// The [`Inputs`] of one step
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputFrame {
+    /// The step, see [`Time::tick`]
+    pub tick: u64,
+    /// The inputs during the step
+    pub inputs: Inputs,
+}
+
+/// The seed and inputs of a game
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Replay {
+    /// The seed of the [`Rng`]
+    pub seed: u64,
+    /// Every step with any input, in order
+    pub frames: Vec<InputFrame>,
+}
+
+/// Errors returned when loading replays
+#[derive(Debug)]
+pub enum ReplayError {
+    /// The file could not be read
+    Io {
+        /// The file
+        path: PathBuf,
+        /// The reason
+        error: io::Error,
+    },
+    /// A line could not be parsed
+    Parse {
+        /// The file
+        path: PathBuf,
+        /// The line number, starting from 1
+        line: usize,
+        /// The reason
+        message: String,
+    },
+}
+
+impl std::error::Error for ReplayError {}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::Io { path, error } => write!(f, "{}: {error}", path.display()),
+            ReplayError::Parse { path, line, message } => write!(f, "{}:{line}: {message}", path.display()),
+        }
+    }
+}
+
+impl Replay {
+    /// Read and parse a replay file
+    pub fn load(path: impl AsRef<Path>) -> Result<Replay, ReplayError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|error| ReplayError::Io { path: path.to_owned(), error })?;
+        Replay::parse(path, &source)
+    }
+
+    /// Parse the replay in `source`, `path` is only used for errors
+    pub fn parse(path: impl AsRef<Path>, source: &str) -> Result<Replay, ReplayError> {
+        let mut seed = None;
+        let mut frames: Vec<InputFrame> = Vec::new();
+        for (index, line) in source.lines().enumerate() {
+            let error = |message| ReplayError::Parse { path: path.as_ref().to_owned(), line: index + 1, message };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if seed.is_none() {
+                let value = line.strip_prefix("seed").and_then(|rest| rest.trim_start().strip_prefix('=')).ok_or_else(|| error(format!("expected `seed = <number>`, found `{line}`")))?;
+                seed = Some(value.trim().parse().map_err(|_| error(format!("invalid seed `{}`", value.trim())))?);
+                continue;
+            }
+
+            let sets = line.split('|').collect::<Vec<_>>();
+            let (pressed, just_pressed, just_released, typed) = match sets.as_slice() {
+                [pressed, just_pressed, just_released] => (pressed, just_pressed, just_released, ""),
+                [pressed, just_pressed, just_released, typed] => (pressed, just_pressed, just_released, *typed),
+                _ => return Err(error(format!("expected `<step> <keys> | <keys> | <keys> [| <keys>]`, found `{line}`"))),
+            };
+            let mut pressed = pressed.split_whitespace();
+            let tick = pressed.next().ok_or_else(|| error("missing step number".to_owned()))?;
+            let tick: u64 = tick.parse().map_err(|_| error(format!("invalid step number `{tick}`")))?;
+            if let Some(previous) = frames.last()
+                && previous.tick >= tick
+            {
+                return Err(error(format!("step {tick} is not after step {}", previous.tick)));
+            }
+            let keys = |keys: &mut dyn Iterator<Item = &str>| keys.map(parse_key).collect::<Result<HashSet<_>, _>>().map_err(error);
+            let typed = typed.split_whitespace().map(parse_key).collect::<Result<Vec<_>, _>>().map_err(error)?;
+            let inputs = Inputs { pressed: keys(&mut pressed)?, just_pressed: keys(&mut just_pressed.split_whitespace())?, just_released: keys(&mut just_released.split_whitespace())?, typed };
+            frames.push(InputFrame { tick, inputs });
+        }
+        let Some(seed) = seed else { return Err(ReplayError::Parse { path: path.as_ref().to_owned(), line: source.lines().count().max(1), message: "missing `seed = <number>`".to_owned() }) };
+        Ok(Replay { seed, frames })
+    }
+
+    /// Write the replay to a file
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+}
+
+impl std::fmt::Display for Replay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Sorted so the same inputs are always written the same way
+        let keys = |keys: &HashSet<KeyCode>| {
+            let mut names = keys.iter().copied().map(format_key).collect::<Vec<_>>();
+            names.sort();
+            names.join(" ")
+        };
+        writeln!(f, "seed = {}", self.seed)?;
+        for InputFrame { tick, inputs } in &self.frames {
+            let mut line = format!("{tick} {} | {} | {}", keys(&inputs.pressed), keys(&inputs.just_pressed), keys(&inputs.just_released));
+            // Kept in the order they were typed
+            if !inputs.typed.is_empty() {
+                line += &format!(" | {}", inputs.typed.iter().copied().map(format_key).collect::<Vec<_>>().join(" "));
+            }
+            writeln!(f, "{}", line.split_whitespace().collect::<Vec<_>>().join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// A global resource for the replay being recorded
+pub struct Recording(pub Replay);
+
+/// A global resource for the replay being played back
+pub struct Playback {
+    /// The replay
+    pub replay: Replay,
+    /// The index of the next frame in [`Replay::frames`]
+    pub next: usize,
+}
+
+impl Playback {
+    /// Play back the replay from its start
+    pub fn new(replay: Replay) -> Playback {
+        Playback { replay, next: 0 }
+    }
+}
+
+/// Add the [`Inputs`] of this step to the [`Recording`], if any key is involved
+pub fn record_inputs_system(time: Res<Time>, inputs: Res<Inputs>, mut recording: ResMut<Recording>) {
+    if inputs.pressed.is_empty() && inputs.just_pressed.is_empty() && inputs.just_released.is_empty() && inputs.typed.is_empty() {
+        return;
+    }
+    recording.0.frames.push(InputFrame { tick: time.tick, inputs: inputs.clone() });
+}
+
+/// Set [`Inputs`] to the ones recorded for this step in the [`Playback`]
+pub fn playback_inputs_system(time: Res<Time>, mut inputs: ResMut<Inputs>, mut playback: ResMut<Playback>) {
+    let playback = &mut *playback;
+    match playback.replay.frames.get(playback.next) {
+        Some(frame) if frame.tick == time.tick => {
+            *inputs = frame.inputs.clone();
+            playback.next += 1;
+        }
+        _ => *inputs = Inputs::default(),
+    }
+}
+
+/// Register the system recording the [`Inputs`] to the [`Schedule`], needs the [`Recording`] resource
+///
+/// [`Inputs`] only change during [`Stage::Input`], so the system records them afterwards.
+pub fn add_recording_systems(schedule: &mut Schedule) {
+    schedule.add_system(Stage::PostUpdate, "record_inputs", record_inputs_system);
+}
+
+/// Register the system playing back the [`Inputs`] to the [`Schedule`], needs the [`Playback`] resource
+///
+/// Replaces the `input` system reading the keyboard.
+pub fn add_playback_systems(schedule: &mut Schedule) {
+    schedule.add_system(Stage::Input, "playback_inputs", playback_inputs_system).before("inspector_input");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn keys<const N: usize>(keys: [KeyCode; N]) -> HashSet<KeyCode> {
+        keys.into_iter().collect()
+    }
+
+    #[test]
+    fn replay_is_written_and_parsed_back() {
+        let frame = |tick, pressed, just_pressed, just_released, typed| InputFrame { tick, inputs: Inputs { pressed, just_pressed, just_released, typed } };
+        let replay = Replay {
+            seed: 1234,
+            frames: vec![frame(3, keys([KeyCode::Char('w'), KeyCode::Up]), keys([KeyCode::Char('w'), KeyCode::Up]), keys([]), vec![KeyCode::Up, KeyCode::Char('w')]), frame(4, keys([KeyCode::Char(' '), KeyCode::Char('|'), KeyCode::F(1)]), keys([KeyCode::F(1)]), keys([KeyCode::Char('w'), KeyCode::Up]), vec![KeyCode::F(1)]), frame(7, keys([]), keys([KeyCode::Char('b'), KeyCode::Char('a')]), keys([KeyCode::Char('b'), KeyCode::Char('a')]), vec![KeyCode::Char('b'), KeyCode::Char('a'), KeyCode::Char('b')]), frame(10, keys([]), keys([]), keys([KeyCode::Esc]), vec![])],
+        };
+        let source = replay.to_string();
+
+        assert_eq!(source, "seed = 1234\n3 Up w | Up w | | Up w\n4 F1 U+0020 U+007C | F1 | Up w | F1\n7 | a b | a b | b a b\n10 | | Esc\n");
+        assert_eq!(Replay::parse("test.replay", &source).unwrap(), replay);
+    }
+
+    #[test]
+    fn every_key_is_written_and_parsed_back() {
+        // The keys the game reads, then the rest of the keys and characters that need escaping.
+        let game = [KeyCode::Char('w'), KeyCode::Char('a'), KeyCode::Char('s'), KeyCode::Char('d'), KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right, KeyCode::F(1), KeyCode::Char('i'), KeyCode::Char('j'), KeyCode::Char('k'), KeyCode::Char('l'), KeyCode::Enter, KeyCode::Backspace, KeyCode::Esc];
+        let others = [KeyCode::Home, KeyCode::End, KeyCode::PageUp, KeyCode::PageDown, KeyCode::Tab, KeyCode::BackTab, KeyCode::Delete, KeyCode::Insert, KeyCode::F(12), KeyCode::Char(' '), KeyCode::Char('|'), KeyCode::Char('\t'), KeyCode::Char('#'), KeyCode::Char('F'), KeyCode::Char('U'), KeyCode::Char('é')];
+        let all = game.into_iter().chain(others).collect::<HashSet<_>>();
+        let replay = Replay { seed: 1, frames: vec![InputFrame { tick: 0, inputs: Inputs { pressed: all.clone(), just_pressed: all.clone(), just_released: all.clone(), typed: all.into_iter().collect() } }] };
+
+        assert_eq!(Replay::parse("test.replay", &replay.to_string()).unwrap(), replay);
+    }
+
+    #[test]
+    fn parse_errors_point_to_the_line() {
+        let error = |source| Replay::parse("run.replay", source).err().unwrap().to_string();
+
+        assert_eq!(error("# Nothing\n3 w | w |"), "run.replay:2: expected `seed = <number>`, found `3 w | w |`");
+        assert_eq!(error("seed = -1"), "run.replay:1: invalid seed `-1`");
+        assert_eq!(error("seed = 1\n3 w | w"), "run.replay:2: expected `<step> <keys> | <keys> | <keys> [| <keys>]`, found `3 w | w`");
+        assert_eq!(error("seed = 1\n3 | | | w wa"), "run.replay:2: invalid key `wa`");
+        assert_eq!(error("seed = 1\n3 w | wa |"), "run.replay:2: invalid key `wa`");
+        assert_eq!(error("seed = 1\n3 w | w |\n3 | | w"), "run.replay:3: step 3 is not after step 3");
+        assert_eq!(error(""), "run.replay:1: missing `seed = <number>`");
+        assert!(matches!(Replay::load("missing/run.replay"), Err(ReplayError::Io { .. })));
+    }
+}
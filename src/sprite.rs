@@ -2,38 +2,118 @@
 
 use crate::prelude::*;
 
+/// A terminal color
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    Reset,
+    Black,
+    DarkGrey,
+    Red,
+    DarkRed,
+    Green,
+    DarkGreen,
+    Yellow,
+    DarkYellow,
+    Blue,
+    DarkBlue,
+    Magenta,
+    DarkMagenta,
+    Cyan,
+    DarkCyan,
+    White,
+    Grey,
+    Rgb {
+        r: u8,
+        g: u8,
+        b: u8,
+    },
+    /// One of the 256 ANSI colors
+    AnsiValue(u8),
+}
+
+#[cfg(feature = "terminal")]
+impl From<Color> for style::Color {
+    fn from(color: Color) -> style::Color {
+        match color {
+            Color::Reset => style::Color::Reset,
+            Color::Black => style::Color::Black,
+            Color::DarkGrey => style::Color::DarkGrey,
+            Color::Red => style::Color::Red,
+            Color::DarkRed => style::Color::DarkRed,
+            Color::Green => style::Color::Green,
+            Color::DarkGreen => style::Color::DarkGreen,
+            Color::Yellow => style::Color::Yellow,
+            Color::DarkYellow => style::Color::DarkYellow,
+            Color::Blue => style::Color::Blue,
+            Color::DarkBlue => style::Color::DarkBlue,
+            Color::Magenta => style::Color::Magenta,
+            Color::DarkMagenta => style::Color::DarkMagenta,
+            Color::Cyan => style::Color::Cyan,
+            Color::DarkCyan => style::Color::DarkCyan,
+            Color::White => style::Color::White,
+            Color::Grey => style::Color::Grey,
+            Color::Rgb { r, g, b } => style::Color::Rgb { r, g, b },
+            Color::AnsiValue(value) => style::Color::AnsiValue(value),
+        }
+    }
+}
+
 /// A component that display [`char`]
 #[derive(Default, Clone)]
 pub struct Sprite {
     /// The [`char`]
     pub char: char,
-    /// The style to display
-    pub style: style::ContentStyle,
+    /// The foreground color, the terminal's own if [`None`]
+    pub color: Option<Color>,
+}
+
+impl Component for Sprite {}
+
+impl Reflect for Sprite {
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        let color = self.color.map_or_else(|| "none".to_owned(), format_color);
+        vec![("char", self.char.to_string()), ("color", color)]
+    }
+
+    fn set_field(&mut self, name: &str, value: &str) -> Result<(), ReflectError> {
+        match name {
+            "char" => self.char = parse_field(name, value)?,
+            "color" if value.trim() == "none" => self.color = None,
+            "color" => self.color = Some(parse_color(value.trim()).map_err(|_| ReflectError::InvalidValue { field: name.to_owned(), value: value.to_owned() })?),
+            _ => return Err(ReflectError::UnknownField(name.to_owned())),
+        }
+        Ok(())
+    }
 }
 
 /// Render [`Sprite`] to the terminal
-pub fn sprite_system(stdout: &mut StdoutLock, camera: Entity, entities: &Entities, positions: &Components<Vec2i32>, sprites: &Components<Sprite>, damaged_timer: &Components<Timer>, damaged_color: &Components<Color>) {
-    let camera_pos = *positions.get(entities, camera).unwrap();
+#[cfg(feature = "terminal")]
+pub fn sprite_system(cameras: Query<&Vec2i32, With<Camera>>, sprites: Query<(&Vec2i32, &Sprite, Option<&DamagedTimer>, Option<&DamagedColor>)>) {
+    let stdout = &mut io::stdout().lock();
+    let camera_pos = *cameras.single().expect("Camera position");
     let terminal_size = terminal::size().expect("Terminal size");
     let terminal_size = (terminal_size.0 as i32, terminal_size.1 as i32);
     let terminal_middle = (terminal_size.0 / 2, terminal_size.1 / 2);
-    for id in entities.iter() {
-        let Ok(position) = positions.get(entities, id) else { continue };
-        let Ok(sprite) = sprites.get(entities, id) else { continue };
-
+    for (position, sprite, damaged_timer, damaged_color) in &sprites {
         let terminal_pos = (terminal_middle.0 + position.0 - camera_pos.0, terminal_middle.1 + position.1 - camera_pos.1);
         if !pos_in_size(terminal_pos, terminal_size) {
             continue;
         }
         let mut content = sprite.char.stylize();
-        *content.style_mut() = sprite.style;
+        content.style_mut().foreground_color = sprite.color.map(Into::into);
         // Special effect when an entity is damaged
-        if let Ok(timer) = damaged_timer.get(entities, id)
-            && let Ok(color) = damaged_color.get(entities, id)
-            && !timer.finished()
+        if let Some(timer) = damaged_timer
+            && let Some(color) = damaged_color
+            && !timer.0.finished()
         {
-            content.style_mut().foreground_color = Some(*color);
+            content.style_mut().foreground_color = Some(color.0.into());
         }
         let _ = queue!(stdout, cursor::MoveTo(terminal_pos.0 as u16, terminal_pos.1 as u16), style::Print(content));
     }
 }
+
+/// Register the sprite systems to the [`Schedule`]
+#[cfg(feature = "terminal")]
+pub fn add_sprite_systems(schedule: &mut Schedule) {
+    schedule.add_system(Stage::Render, "sprite", sprite_system).after("draw");
+}
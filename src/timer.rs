@@ -2,40 +2,366 @@
 
 use crate::prelude::*;
 
+/// What a [`Timer`] does once it finishes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimerMode {
+    /// Stay finished until it is reset or [`Timer::next_cycle`] is called
+    #[default]
+    Once,
+    /// Start again right away, keeping the time past `max`
+    Repeating,
+}
+
 /// A timer
+///
+/// Ticked by [`timer_system`] for components, or by hand with [`Timer::tick`].
 #[derive(Default, Clone)]
 pub struct Timer {
     /// The amount duration passed
     pub current: Duration,
     /// The duration the timer finishes
     pub max: Duration,
+    /// What the timer does once it finishes
+    pub mode: TimerMode,
+    /// Whether [`Timer::tick`] is ignored
+    pub paused: bool,
+    /// How many times the last tick finished the timer
+    times_finished: u32,
 }
 impl Timer {
     /// Create a new timer with `max` duration and with `current` zero
     pub fn new(max: Duration) -> Timer {
-        Timer { current: Duration::ZERO, max }
+        Timer { current: Duration::ZERO, max, ..Default::default() }
     }
 
     /// Create a new timer with `max` duration and with `current` at `max`
     pub fn new_ended(max: Duration) -> Timer {
-        Timer { current: max, max }
+        Timer { current: max, max, ..Default::default() }
+    }
+
+    /// Create a new [`TimerMode::Repeating`] timer with `max` duration and with `current` zero
+    pub fn repeating(max: Duration) -> Timer {
+        Timer { current: Duration::ZERO, max, mode: TimerMode::Repeating, ..Default::default() }
+    }
+
+    /// Advance `current` by `delta` unless paused
+    ///
+    /// A [`TimerMode::Once`] timer stops advancing once finished, keeping only
+    /// the time past `max` of the tick that finished it. A
+    /// [`TimerMode::Repeating`] timer wraps around and keeps that time for the
+    /// next cycle, a tick longer than `max` finishes it more than once.
+    pub fn tick(&mut self, delta: Duration) {
+        self.times_finished = 0;
+        if self.paused || (self.mode == TimerMode::Once && self.finished()) {
+            return;
+        }
+        self.current += delta;
+        if !self.finished() {
+            return;
+        }
+        self.times_finished = 1;
+        if self.mode == TimerMode::Repeating {
+            if self.max.is_zero() {
+                self.current = Duration::ZERO;
+            } else {
+                self.times_finished = u32::try_from(self.current.as_nanos() / self.max.as_nanos()).unwrap_or(u32::MAX);
+                self.current = Duration::from_nanos((self.current.as_nanos() % self.max.as_nanos()) as u64);
+            }
+        }
     }
 
     /// Set `current` to zero
     pub fn reset(&mut self) {
         self.current = Duration::ZERO;
+        self.times_finished = 0;
+    }
+
+    /// Start the next cycle of a finished [`TimerMode::Once`] timer, keeping the time past `max`
+    ///
+    /// Unlike [`Timer::reset`], a timer restarted on every finish does not drift late.
+    pub fn next_cycle(&mut self) {
+        self.current = self.current.saturating_sub(self.max);
+        self.times_finished = 0;
+    }
+
+    /// Stop [`Timer::tick`] from advancing the timer
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Let [`Timer::tick`] advance the timer again
+    pub fn resume(&mut self) {
+        self.paused = false;
     }
 
     /// Check if `current` equals or more than `max`
+    ///
+    /// A [`TimerMode::Repeating`] timer is only finished on the tick it wrapped around.
     pub fn finished(&self) -> bool {
-        self.current >= self.max
+        match self.mode {
+            TimerMode::Once => self.current >= self.max,
+            TimerMode::Repeating => self.just_finished() || self.current >= self.max,
+        }
+    }
+
+    /// Check if the last [`Timer::tick`] finished the timer
+    pub fn just_finished(&self) -> bool {
+        self.times_finished > 0
+    }
+
+    /// How many times the last [`Timer::tick`] finished the timer
+    ///
+    /// Only a [`TimerMode::Repeating`] timer ticked by more than `max` finishes
+    /// more than once, systems should act once per cycle so none is dropped.
+    pub fn times_finished_this_tick(&self) -> u32 {
+        self.times_finished
+    }
+
+    /// How far the timer is, from `0.0` to `1.0`
+    pub fn fraction(&self) -> f32 {
+        if self.max.is_zero() {
+            return 1.0;
+        }
+        (self.current.as_secs_f32() / self.max.as_secs_f32()).min(1.0)
+    }
+}
+
+impl Reflect for Timer {
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        let mode = match self.mode {
+            TimerMode::Once => "once",
+            TimerMode::Repeating => "repeating",
+        };
+        vec![("current", format_duration(self.current)), ("max", format_duration(self.max)), ("mode", mode.to_owned()), ("paused", self.paused.to_string())]
+    }
+
+    fn set_field(&mut self, name: &str, value: &str) -> Result<(), ReflectError> {
+        let duration = parse_duration(value).map_err(|_| ReflectError::InvalidValue { field: name.to_owned(), value: value.to_owned() });
+        match name {
+            "current" => self.current = duration?,
+            "max" => self.max = duration?,
+            "mode" => {
+                self.mode = match value.trim() {
+                    "once" => TimerMode::Once,
+                    "repeating" => TimerMode::Repeating,
+                    _ => return Err(ReflectError::InvalidValue { field: name.to_owned(), value: value.to_owned() }),
+                }
+            }
+            "paused" => self.paused = parse_field(name, value)?,
+            _ => return Err(ReflectError::UnknownField(name.to_owned())),
+        }
+        Ok(())
+    }
+}
+
+/// A global resource for the time passed during the current step, see [`FixedTimestep`]
+///
+/// Gameplay timers use [`Time::delta`], which is scaled and stops while
+/// paused. UI timers use `real_delta` so they keep running at the real speed.
+pub struct Time {
+    /// The unscaled time of the current step
+    pub real_delta: Duration,
+    /// How fast the game time passes compared to the real time, `0.5` for slow motion
+    pub scale: f64,
+    /// Whether the game time is stopped, gameplay systems also stop with [`not_paused`]
+    pub paused: bool,
+    /// The number of the current step, counting from zero
+    pub tick: u64,
+}
+impl Default for Time {
+    fn default() -> Self {
+        Time { real_delta: Duration::ZERO, scale: 1.0, paused: false, tick: 0 }
+    }
+}
+impl Time {
+    /// The game time of the current step, `real_delta` times `scale` or zero while paused
+    pub fn delta(&self) -> Duration {
+        if self.paused { Duration::ZERO } else { self.real_delta.mul_f64(self.scale.max(0.0)) }
+    }
+}
+
+/// Run condition for systems that only run while [`Time::paused`] is not set
+pub fn not_paused(time: Res<Time>) -> bool {
+    !time.paused
+}
+
+/// How many times per second the game logic runs
+pub const TICK_RATE: u32 = 60;
+
+/// The most steps ran in one frame to catch up with the real time
+pub const MAX_TICKS_PER_FRAME: u32 = 5;
+
+/// The lowest frame rate at which [`MAX_TICKS_PER_FRAME`] keeps up with [`TICK_RATE`]
+///
+/// Slower frames need more steps than allowed, and the game runs slower than the real time.
+pub const MIN_FPS: u32 = TICK_RATE.div_ceil(MAX_TICKS_PER_FRAME);
+
+/// Turns the real time passed into a number of fixed simulation steps
+///
+/// Time shorter than a step is kept for the next frame. When more than
+/// `max_steps` are due, the rest is dropped so the game slows down instead of
+/// running ever more steps to catch up.
+pub struct FixedTimestep {
+    /// The time simulated by one step
+    pub step: Duration,
+    /// The most steps ran in one frame
+    pub max_steps: u32,
+    /// The real time not simulated yet
+    accumulator: Duration,
+}
+impl FixedTimestep {
+    /// Create a new timestep running `rate` steps per second
+    pub fn new(rate: u32, max_steps: u32) -> FixedTimestep {
+        FixedTimestep { step: Duration::from_secs(1) / rate, max_steps, accumulator: Duration::ZERO }
+    }
+
+    /// Add the real time passed since the last frame and return how many steps to run
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        self.accumulator += elapsed;
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            if steps == self.max_steps {
+                self.accumulator = Duration::ZERO;
+                break;
+            }
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        steps
+    }
+}
+
+/// Increment all timers of the component `T` by [`Time::delta`]
+pub fn timer_system<T: Component + AsMut<Timer>>(time: Res<Time>, mut query: Query<&mut T>) {
+    for t in &mut query {
+        t.as_mut().tick(time.delta());
     }
 }
 
-/// Increment all timers by delta time
-pub fn timer_system(delta: Duration, entities: &Entities, timers: &mut Components<Timer>) {
-    for id in entities.iter() {
-        let Ok(t) = timers.get_mut(entities, id) else { continue };
-        t.current += delta;
+/// Increment all timers of the component `T` by [`Time::real_delta`], ignoring the scale and pause
+pub fn real_timer_system<T: Component + AsMut<Timer>>(time: Res<Time>, mut query: Query<&mut T>) {
+    for t in &mut query {
+        t.as_mut().tick(time.real_delta);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn once_timer_carries_the_overshoot_of_one_tick() {
+        let mut timer = Timer::new(Duration::from_millis(300));
+        timer.tick(Duration::from_millis(150));
+        assert!(!timer.finished());
+        assert_eq!(timer.fraction(), 0.5);
+        timer.tick(Duration::from_millis(200));
+        assert!(timer.finished() && timer.just_finished());
+        // Finished timers wait without piling up more time
+        timer.tick(Duration::from_millis(500));
+        assert!(timer.finished() && !timer.just_finished());
+        assert_eq!(timer.fraction(), 1.0);
+
+        timer.next_cycle();
+        assert_eq!(timer.current, Duration::from_millis(50));
+        timer.pause();
+        timer.tick(Duration::from_secs(1));
+        assert_eq!(timer.current, Duration::from_millis(50));
+        timer.resume();
+        timer.tick(Duration::from_millis(250));
+        assert!(timer.just_finished());
+        timer.reset();
+        assert_eq!(timer.current, Duration::ZERO);
+    }
+
+    #[test]
+    fn repeating_timer_wraps_around() {
+        let mut timer = Timer::repeating(Duration::from_millis(300));
+        let mut finishes = 0;
+        for _ in 0..10 {
+            timer.tick(Duration::from_millis(100));
+            if timer.just_finished() {
+                finishes += 1;
+            }
+        }
+        assert_eq!(finishes, 3);
+        assert_eq!(timer.current, Duration::from_millis(100));
+        assert!(!timer.finished());
+
+        timer.tick(Duration::from_millis(250));
+        assert!(timer.finished());
+        assert_eq!(timer.current, Duration::from_millis(50));
+    }
+
+    #[test]
+    fn long_ticks_count_every_cycle() {
+        let mut timer = Timer::repeating(Duration::from_millis(200));
+        timer.tick(Duration::from_millis(500));
+        assert_eq!(timer.times_finished_this_tick(), 2);
+        assert_eq!(timer.current, Duration::from_millis(100));
+
+        timer.tick(Duration::from_millis(50));
+        assert_eq!(timer.times_finished_this_tick(), 0);
+        assert!(!timer.just_finished());
+    }
+
+    #[test]
+    fn fixed_timestep_keeps_the_remainder_and_caps_steps() {
+        let mut timestep = FixedTimestep::new(100, 3);
+        assert_eq!(timestep.step, Duration::from_millis(10));
+
+        assert_eq!(timestep.advance(Duration::from_millis(4)), 0);
+        assert_eq!(timestep.advance(Duration::from_millis(17)), 2);
+        assert_eq!(timestep.advance(Duration::from_millis(9)), 1);
+        // Falling far behind only runs `max_steps` and drops the rest
+        assert_eq!(timestep.advance(Duration::from_secs(2)), 3);
+        assert_eq!(timestep.advance(Duration::from_millis(9)), 0);
+    }
+
+    #[test]
+    fn min_fps_keeps_up_with_the_tick_rate() {
+        let ticks_in_one_second = |fps: u32| {
+            let mut timestep = FixedTimestep::new(TICK_RATE, MAX_TICKS_PER_FRAME);
+            (0..fps).map(|_| timestep.advance(Duration::from_secs(1) / fps)).sum::<u32>()
+        };
+
+        assert_eq!(ticks_in_one_second(MIN_FPS), TICK_RATE);
+        assert_eq!(ticks_in_one_second(30), TICK_RATE);
+        assert!(ticks_in_one_second(MIN_FPS - 1) < TICK_RATE);
+    }
+
+    struct GameTimer(Timer);
+    impl Component for GameTimer {}
+    impl AsMut<Timer> for GameTimer {
+        fn as_mut(&mut self) -> &mut Timer {
+            &mut self.0
+        }
+    }
+
+    struct UiTimer(Timer);
+    impl Component for UiTimer {}
+    impl AsMut<Timer> for UiTimer {
+        fn as_mut(&mut self) -> &mut Timer {
+            &mut self.0
+        }
+    }
+
+    #[test]
+    fn game_timers_follow_the_time_scale() {
+        let mut world = World::default();
+        world.insert_resource(Time { real_delta: Duration::from_millis(100), scale: 0.5, ..Default::default() });
+        let entity = world.spawn();
+        world.insert(entity, GameTimer(Timer::new(Duration::from_secs(1)))).unwrap();
+        world.insert(entity, UiTimer(Timer::new(Duration::from_secs(1)))).unwrap();
+        let mut schedule = Schedule::default();
+        schedule.add_system(Stage::Update, "game_timer", timer_system::<GameTimer>);
+        schedule.add_system(Stage::Update, "ui_timer", real_timer_system::<UiTimer>);
+
+        schedule.run(&mut world);
+        world.resource_mut::<Time>().paused = true;
+        schedule.run(&mut world);
+
+        assert_eq!(world.query::<&GameTimer>().get(entity).unwrap().0.current, Duration::from_millis(50));
+        assert_eq!(world.query::<&UiTimer>().get(entity).unwrap().0.current, Duration::from_millis(200));
     }
 }
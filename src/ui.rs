@@ -2,9 +2,35 @@
 
 use crate::prelude::*;
 
+/// A marker component for the entity that the view is centered on
+pub struct Camera;
+
+impl Component for Camera {
+    const STORAGE: StorageKind = StorageKind::Marker;
+}
+
+/// Clear the terminal and start a frame
+#[cfg(feature = "terminal")]
+pub fn begin_render_system() {
+    let stdout = &mut io::stdout().lock();
+    // Syncronize to prevent tearing
+    queue!(stdout, terminal::BeginSynchronizedUpdate, terminal::Clear(terminal::ClearType::Purge)).unwrap();
+}
+
+/// Show everything drawn during the frame
+#[cfg(feature = "terminal")]
+pub fn end_render_system() {
+    let stdout = &mut io::stdout().lock();
+    queue!(stdout, terminal::EndSynchronizedUpdate, cursor::RestorePosition).unwrap();
+    stdout.flush().expect("Flush stdout");
+}
+
 /// Visualize the wall at the top and bottom
-pub fn visualize_arena_wall_system(stdout: &mut StdoutLock, arena: &Vec2i32, camera: Entity, entities: &Entities, positions: &Components<Vec2i32>) {
-    let camera_pos = *positions.get(entities, camera).unwrap();
+#[cfg(feature = "terminal")]
+pub fn visualize_arena_wall_system(arena: Res<Arena>, cameras: Query<&Vec2i32, With<Camera>>) {
+    let stdout = &mut io::stdout().lock();
+    let arena = &arena.0;
+    let camera_pos = *cameras.single().expect("Camera position");
     let terminal_size = terminal::size().expect("Terminal size");
     let terminal_size = (terminal_size.0 as i32, terminal_size.1 as i32);
     let terminal_middle = (terminal_size.0 / 2, terminal_size.1 / 2);
@@ -32,14 +58,17 @@ pub fn visualize_arena_wall_system(stdout: &mut StdoutLock, arena: &Vec2i32, cam
 }
 
 /// Display stats about the game and player
-pub fn hud_system(stdout: &mut StdoutLock, arena: &Vec2i32, score: &i32, player: &Player, entities: &Entities, hps: &Components<Health>, max_hps: &Components<Health>) {
+#[cfg(feature = "terminal")]
+pub fn hud_system(arena: Res<Arena>, score: Res<Score>, players: Query<(&Health, &MaxHealth, &Weapon), With<PlayerControlled>>) {
+    let stdout = &mut io::stdout().lock();
+    let arena = &arena.0;
+    let score = score.0;
+
     let terminal_size = terminal::size().expect("Terminal size");
     let terminal_size = (terminal_size.0 as i32, terminal_size.1 as i32);
     let terminal_middle = (terminal_size.0 / 2, terminal_size.1 / 2);
 
-    let hp = hps.get(entities, player.id).unwrap();
-    let max_hp = max_hps.get(entities, player.id).unwrap();
-    let weapon = &player.primary_weapon;
+    let Some((Health(hp), MaxHealth(max_hp), weapon)) = players.single() else { return };
 
     let terminal_pos = (terminal_middle.0 - arena.0, terminal_middle.1 + arena.1 + 2);
     if !pos_in_size(terminal_pos, terminal_size) {
@@ -50,15 +79,35 @@ pub fn hud_system(stdout: &mut StdoutLock, arena: &Vec2i32, score: &i32, player:
     let _ = queue!(stdout, cursor::MoveTo(terminal_pos.0 as u16, terminal_pos.1 as u16 + 2), style::Print(format!("Score : {score}")));
 }
 
-/// Show end screen after the player died
-pub fn display_end_screen_system(stdout: &mut StdoutLock, score: &i32) {
+/// Show end screen after the player died, with the seed to play the same game again
+#[cfg(feature = "terminal")]
+pub fn display_end_screen_system(score: Res<Score>, rng: Res<Rng>) {
+    let stdout = &mut io::stdout().lock();
+    let score = score.0;
     let terminal_size = terminal::size().expect("Terminal size");
     let terminal_size = (terminal_size.0 as i32, terminal_size.1 as i32);
     let terminal_middle = (terminal_size.0 / 2, terminal_size.1 / 2);
     let top_text = "You died!";
     let bottom_text = format!("Score: {score}");
     let top_text_pos = (terminal_middle.0 - top_text.len() as i32 / 2, terminal_middle.1);
+    let seed_text = format!("Seed: {}", rng.seed());
     let bottom_text_pos = (terminal_middle.0 - bottom_text.len() as i32 / 2, terminal_middle.1 + 1);
+    let seed_text_pos = (terminal_middle.0 - seed_text.len() as i32 / 2, terminal_middle.1 + 2);
     let _ = queue!(stdout, cursor::MoveTo(top_text_pos.0 as u16, top_text_pos.1 as u16), style::Print(top_text));
     let _ = queue!(stdout, cursor::MoveTo(bottom_text_pos.0 as u16, bottom_text_pos.1 as u16), style::Print(bottom_text));
+    let _ = queue!(stdout, cursor::MoveTo(seed_text_pos.0 as u16, seed_text_pos.1 as u16), style::Print(seed_text));
+}
+
+/// Register the UI systems to the [`Schedule`], including the drawing and inspector panel
+///
+/// Every other system in [`Stage::Render`] should run between `begin_render` and `end_render`.
+#[cfg(feature = "terminal")]
+pub fn add_ui_systems(schedule: &mut Schedule) {
+    schedule.add_system(Stage::Render, "begin_render", begin_render_system);
+    schedule.add_system(Stage::Render, "draw", draw_system).after("begin_render");
+    schedule.add_system(Stage::Render, "arena_wall", visualize_arena_wall_system).after("sprite");
+    schedule.add_system(Stage::Render, "hud", hud_system).after("arena_wall");
+    schedule.add_system(Stage::Render, "inspector", inspector_system).after("hud");
+    schedule.add_system(Stage::Render, "end_screen", display_end_screen_system).after("inspector").run_if(game_over);
+    schedule.add_system(Stage::Render, "end_render", end_render_system).after("end_screen");
 }
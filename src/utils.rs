@@ -2,6 +2,10 @@
 
 use crate::prelude::*;
 
+/// A global resource for the size of the arena, extending from its center
+/// to each edge.
+pub struct Arena(pub Vec2i32);
+
 /// Convert position within arena to the collider grid
 pub fn arena_collider_pos(arena: &Vec2i32, pos: Vec2i32) -> Vec2usize {
     ((arena.0 + pos.0) as usize, (arena.1 + pos.1) as usize)
@@ -11,3 +15,116 @@ pub fn arena_collider_pos(arena: &Vec2i32, pos: Vec2i32) -> Vec2usize {
 pub fn pos_in_size(pos: Vec2i32, rect: Vec2i32) -> bool {
     pos.0 >= 0 && pos.1 >= 0 && pos.0 < rect.0 && pos.1 < rect.1
 }
+
+/// Either a color name such as `red` or `dark_blue`, or an ANSI value from 0 to 255
+pub fn parse_color(value: &str) -> Result<Color, String> {
+    if let Ok(value) = value.parse() {
+        return Ok(Color::AnsiValue(value));
+    }
+    let color = match value.to_lowercase().as_str() {
+        "reset" => Color::Reset,
+        "black" => Color::Black,
+        "dark_grey" => Color::DarkGrey,
+        "red" => Color::Red,
+        "dark_red" => Color::DarkRed,
+        "green" => Color::Green,
+        "dark_green" => Color::DarkGreen,
+        "yellow" => Color::Yellow,
+        "dark_yellow" => Color::DarkYellow,
+        "blue" => Color::Blue,
+        "dark_blue" => Color::DarkBlue,
+        "magenta" => Color::Magenta,
+        "dark_magenta" => Color::DarkMagenta,
+        "cyan" => Color::Cyan,
+        "dark_cyan" => Color::DarkCyan,
+        "white" => Color::White,
+        "grey" => Color::Grey,
+        _ => return Err(format!("invalid color `{value}`")),
+    };
+    Ok(color)
+}
+
+/// A duration such as `300ms` or `2s`
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration `{value}`, expected something like `300ms` or `2s`");
+    if let Some(millis) = value.strip_suffix("ms") {
+        millis.trim().parse().map(Duration::from_millis).map_err(|_| invalid())
+    } else if let Some(secs) = value.strip_suffix('s') {
+        secs.trim().parse().map(Duration::from_secs).map_err(|_| invalid())
+    } else {
+        Err(invalid())
+    }
+}
+
+/// Write a color the way [`parse_color`] reads it
+pub fn format_color(color: Color) -> String {
+    match color {
+        Color::AnsiValue(value) => value.to_string(),
+        Color::Rgb { r, g, b } => format!("rgb({r}, {g}, {b})"),
+        // `DarkRed` is written `dark_red`
+        color => {
+            let mut name = String::new();
+            for char in format!("{color:?}").chars() {
+                if char.is_uppercase() && !name.is_empty() {
+                    name.push('_');
+                }
+                name.push(char.to_ascii_lowercase());
+            }
+            name
+        }
+    }
+}
+
+/// A key such as `w`, `Up` or `F1`, other characters are written as their code point such as `U+0020`
+pub fn parse_key(value: &str) -> Result<KeyCode, String> {
+    let mut chars = value.chars();
+    if let (Some(char), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(char));
+    }
+    let key = match value {
+        "Backspace" => KeyCode::Backspace,
+        "Enter" => KeyCode::Enter,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        "Tab" => KeyCode::Tab,
+        "BackTab" => KeyCode::BackTab,
+        "Delete" => KeyCode::Delete,
+        "Insert" => KeyCode::Insert,
+        "Esc" => KeyCode::Esc,
+        _ => {
+            if let Some(code) = value.strip_prefix("U+")
+                && let Some(char) = u32::from_str_radix(code, 16).ok().and_then(char::from_u32)
+            {
+                KeyCode::Char(char)
+            } else if let Some(number) = value.strip_prefix('F')
+                && let Ok(number) = number.parse()
+            {
+                KeyCode::F(number)
+            } else {
+                return Err(format!("invalid key `{value}`"));
+            }
+        }
+    };
+    Ok(key)
+}
+
+/// Write a key the way [`parse_key`] reads it
+pub fn format_key(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(char) if char.is_whitespace() || char.is_control() || char == '|' => format!("U+{:04X}", char as u32),
+        KeyCode::Char(char) => char.to_string(),
+        KeyCode::F(number) => format!("F{number}"),
+        KeyCode::Backspace | KeyCode::Enter | KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down | KeyCode::Home | KeyCode::End | KeyCode::PageUp | KeyCode::PageDown | KeyCode::Tab | KeyCode::BackTab | KeyCode::Delete | KeyCode::Insert | KeyCode::Esc => format!("{key:?}"),
+    }
+}
+
+/// Write a duration the way [`parse_duration`] reads it
+pub fn format_duration(duration: Duration) -> String {
+    format!("{}ms", duration.as_millis())
+}
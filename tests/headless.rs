@@ -0,0 +1,110 @@
+//! Runs the game through the library without a terminal
+
+use rust_minigame::{prelude::*, *};
+
+/// A new game with the built-in prefabs and its schedule
+fn new_game(seed: u64) -> (World, Schedule) {
+    let mut world = World::default();
+    setup_world(&mut world, Prefabs::parse(PREFABS_PATH, DEFAULT_PREFABS).unwrap(), Rng::new(seed));
+    let mut schedule = Schedule::default();
+    add_game_systems(&mut schedule);
+    (world, schedule)
+}
+
+/// Run the game until the player dies, at most ten minutes of game time
+fn play_until_game_over(world: &mut World, schedule: &mut Schedule) {
+    let timestep = FixedTimestep::new(TICK_RATE, MAX_TICKS_PER_FRAME);
+    for _ in 0..TICK_RATE * 60 * 10 {
+        run_tick(world, schedule, timestep.step);
+        if game_over(world.query_filtered()) {
+            break;
+        }
+    }
+}
+
+#[test]
+fn idle_player_is_killed_by_enemies() {
+    let (mut world, mut schedule) = new_game(0);
+    play_until_game_over(&mut world, &mut schedule);
+
+    assert!(game_over(world.query_filtered()));
+    assert_eq!(world.resource::<Score>().0, 0);
+}
+
+#[test]
+fn same_seed_plays_the_same_game() {
+    let enemies = |seed| {
+        let (mut world, mut schedule) = new_game(seed);
+        play_until_game_over(&mut world, &mut schedule);
+        world.query_filtered::<(Entity, &Vec2i32, &Sprite), With<Enemy>>().iter().map(|(entity, pos, sprite)| (entity, *pos, sprite.char)).collect::<Vec<_>>()
+    };
+
+    let first = enemies(7);
+    assert!(!first.is_empty());
+    assert_eq!(first, enemies(7));
+    assert_ne!(first, enemies(8));
+}
+
+#[test]
+fn recorded_inputs_replay_the_same_game() {
+    // Hold the arrow keys toward the closest enemy
+    let scripted = |world: &World| {
+        let mut inputs = Inputs::default();
+        let Some(&player) = world.query_filtered::<&Vec2i32, With<PlayerControlled>>().single() else { return inputs };
+        let closest = world.query_filtered::<&Vec2i32, With<Enemy>>().iter().min_by_key(|enemy| (enemy.0 - player.0).abs().max((enemy.1 - player.1).abs())).copied();
+        let Some(enemy) = closest else { return inputs };
+        for (delta, less, more) in [(enemy.0 - player.0, KeyCode::Left, KeyCode::Right), (enemy.1 - player.1, KeyCode::Up, KeyCode::Down)] {
+            match delta.signum() {
+                -1 => inputs.pressed.insert(less),
+                1 => inputs.pressed.insert(more),
+                _ => false,
+            };
+        }
+        inputs
+    };
+    let state = |world: &World| {
+        let enemies = world.query_filtered::<(Entity, &Vec2i32, &Health), With<Enemy>>().iter().map(|(entity, pos, Health(hp))| (entity, *pos, *hp)).collect::<Vec<_>>();
+        let player = world.query_filtered::<(&Vec2i32, &Health), With<PlayerControlled>>().single().map(|(pos, Health(hp))| (*pos, *hp));
+        (world.resource::<Score>().0, player, enemies)
+    };
+    let timestep = FixedTimestep::new(TICK_RATE, MAX_TICKS_PER_FRAME);
+
+    let (mut world, mut schedule) = new_game(3);
+    world.insert_resource(Recording(Replay { seed: 3, frames: Vec::new() }));
+    add_recording_systems(&mut schedule);
+    for _ in 0..TICK_RATE * 60 {
+        let inputs = scripted(&world);
+        *world.resource_mut::<Inputs>() = inputs;
+        run_tick(&mut world, &mut schedule, timestep.step);
+    }
+    let recorded = state(&world);
+    let replay = Replay::parse("test.replay", &world.resource::<Recording>().0.to_string()).unwrap();
+
+    let (mut world, mut schedule) = new_game(replay.seed);
+    world.insert_resource(Playback::new(replay));
+    add_playback_systems(&mut schedule);
+    for _ in 0..TICK_RATE * 60 {
+        run_tick(&mut world, &mut schedule, timestep.step);
+    }
+
+    assert!(recorded.0 > 0);
+    assert_eq!(state(&world), recorded);
+}
+
+#[test]
+fn weapon_attacks_from_the_moved_position() {
+    let (mut world, mut schedule) = new_game(0);
+    let player = world.query_filtered::<Entity, With<PlayerControlled>>().single().unwrap();
+    world.insert(player, MoveTimer(Timer::new_ended(Duration::from_millis(50)))).unwrap();
+    world.insert(player, WeaponTimer(Timer::new_ended(Weapon::Stick.base_delay()))).unwrap();
+    // Only in reach once the player has moved to the right
+    let mut target = EntityBuilder::default();
+    target.with((4, 0)).with(Collider).with(Health(10));
+    let target = target.spawn(&mut world);
+
+    world.resource_mut::<Inputs>().pressed.extend([KeyCode::Char('d'), KeyCode::Right]);
+    run_tick(&mut world, &mut schedule, FixedTimestep::new(TICK_RATE, MAX_TICKS_PER_FRAME).step);
+
+    assert_eq!(world.query_filtered::<&Vec2i32, With<PlayerControlled>>().single(), Some(&(1, 0)));
+    assert_eq!(world.query::<&Health>().get(target).ok(), Some(&Health(10 - Weapon::Stick.base_damage())));
+}